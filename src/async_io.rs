@@ -0,0 +1,312 @@
+use crate::{
+    error::*,
+    event::{Event, EventMechanism, EventType},
+    session::Session,
+    utility::Timeout,
+};
+use std::{
+    mem,
+    sync::{MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+#[cfg(feature = "tokio")]
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+use visa_bindings::*;
+
+/// Interval in which [`AsyncReadJob::wait`] checks for completions another
+/// waiting job received.
+const COMPLETION_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Completion of an asynchronous job as reported by its I/O completion event.
+#[derive(Debug, Clone, Copy)]
+struct JobCompletion {
+    job_id: ViJobId,
+    status: ViStatus,
+    count: usize,
+}
+
+impl TryFrom<&Event> for JobCompletion {
+    type Error = Error;
+
+    fn try_from(event: &Event) -> Result<Self> {
+        Ok(Self {
+            job_id: event.get_attribute(VI_ATTR_JOB_ID)?,
+            status: event.get_attribute::<ViUInt32>(VI_ATTR_STATUS)? as ViStatus,
+            count: event.get_attribute(VI_ATTR_RET_COUNT)?,
+        })
+    }
+}
+
+/// Asynchronous I/O bookkeeping of a session.
+#[derive(Debug, Default)]
+pub(crate) struct AsyncState {
+    /// Completions dequeued by a job waiting for another one.
+    completions: Vec<JobCompletion>,
+    queue: MechanismState,
+    handler: MechanismState,
+}
+
+impl AsyncState {
+    fn mechanism(&mut self, mechanism: EventMechanism) -> &mut MechanismState {
+        match mechanism {
+            EventMechanism::Handler => &mut self.handler,
+            EventMechanism::Queue | EventMechanism::Suspend => &mut self.queue,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct MechanismState {
+    /// Jobs relying on the mechanism.
+    users: usize,
+    /// Whether the mechanism was enabled for the jobs, and is disabled again
+    /// after the last one.
+    enabled: bool,
+}
+
+/// Use of an I/O completion event mechanism by a job, released on drop.
+#[derive(Debug)]
+struct MechanismGuard<'a> {
+    session: &'a Session,
+    mechanism: EventMechanism,
+}
+
+impl Drop for MechanismGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.session.async_state();
+        let entry = state.mechanism(self.mechanism);
+        entry.users = entry.users.saturating_sub(1);
+        if entry.users > 0 {
+            return;
+        }
+        if entry.enabled {
+            entry.enabled = false;
+            if let Err(error) = self
+                .session
+                .disable_event(EventType::IoCompletion, self.mechanism)
+            {
+                error!("Disabling I/O completion events failed: {}", error);
+            }
+        }
+        if self.mechanism == EventMechanism::Queue {
+            state.completions.clear();
+        }
+    }
+}
+
+/// Read started with [`Session::read_async`].
+///
+/// VISA fills the buffer in the background, so it is only handed back once
+/// the job completed. A job dropped before that is terminated and its buffer
+/// leaked, as VISA may still be writing to it.
+#[derive(Debug)]
+pub struct AsyncReadJob<'a> {
+    session: &'a Session,
+    job_id: ViJobId,
+    buffer: Option<Vec<u8>>,
+    _mechanism: MechanismGuard<'a>,
+}
+
+impl Drop for AsyncReadJob<'_> {
+    fn drop(&mut self) {
+        let Some(buffer) = self.buffer.take() else {
+            return;
+        };
+        if let Err(error) = self.terminate() {
+            error!("Terminating asynchronous read failed with code: {}", error);
+        }
+        mem::forget(buffer);
+        self.take_completion();
+    }
+}
+
+impl AsyncReadJob<'_> {
+    pub fn job_id(&self) -> ViJobId {
+        self.job_id
+    }
+
+    /// Blocks until the read completed, returning the buffer and the number
+    /// of bytes read into it.
+    ///
+    /// Completion events of other jobs on the session are kept for their own
+    /// `wait`. If `timeout` expires the job is dropped, see [`AsyncReadJob`].
+    pub fn wait(self, timeout: Timeout) -> Result<(Vec<u8>, usize)> {
+        let deadline = match timeout {
+            Timeout::Immediate => Some(Instant::now()),
+            Timeout::Custom(duration) => Some(Instant::now() + duration),
+            Timeout::Maximum | Timeout::Infinite => None,
+        };
+
+        loop {
+            if let Some(completion) = self.take_completion() {
+                return self.complete(completion);
+            }
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let slice = remaining.map_or(COMPLETION_POLL_INTERVAL, |remaining| {
+                remaining.min(COMPLETION_POLL_INTERVAL)
+            });
+
+            let event = match self
+                .session
+                .wait_on_event(EventType::IoCompletion, Timeout::Custom(slice))
+            {
+                Ok(event) => event,
+                Err(Error::Timeout) if remaining.is_none_or(|remaining| !remaining.is_zero()) => {
+                    continue;
+                }
+                Err(error) => return Err(error),
+            };
+            let completion = JobCompletion::try_from(&event)?;
+            if completion.job_id == self.job_id {
+                return self.complete(completion);
+            }
+            debug!("Kept completion event of job {}", completion.job_id);
+            self.session.async_state().completions.push(completion);
+        }
+    }
+
+    /// Terminates the read (`viTerminate`) and waits for its completion,
+    /// for at most the session timeout.
+    pub fn abort(self) -> Result<()> {
+        self.terminate()?;
+        let timeout = self.session.timeout()?;
+        match self.wait(timeout) {
+            Ok(_) | Err(Error::Abort) => Ok(()),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Removes the completion of this job another job dequeued.
+    fn take_completion(&self) -> Option<JobCompletion> {
+        let mut state = self.session.async_state();
+        let position = state
+            .completions
+            .iter()
+            .position(|completion| completion.job_id == self.job_id)?;
+        Some(state.completions.swap_remove(position))
+    }
+
+    /// Hands back the buffer once the completion event of the job arrived.
+    fn complete(mut self, completion: JobCompletion) -> Result<(Vec<u8>, usize)> {
+        // The job finished, successfully or not, VISA is done with the buffer.
+        let buffer = self.buffer.take().unwrap_or_default();
+        let completion_code = CompletionCode::try_from(completion.status)?;
+        debug!(
+            "Asynchronous read {} completed with code: {}",
+            self.job_id, completion_code
+        );
+        Ok((buffer, completion.count))
+    }
+
+    fn terminate(&self) -> Result<()> {
+        let completion_code = unsafe {
+            CompletionCode::try_from(viTerminate(
+                self.session.value,
+                VI_NULL as ViUInt16,
+                self.job_id,
+            ))?
+        };
+        debug!(
+            "Asynchronous read {} terminated with code: {}",
+            self.job_id, completion_code
+        );
+        Ok(())
+    }
+}
+
+impl Session {
+    /// Starts reading into `buffer` in the background (`viReadAsync`).
+    ///
+    /// Queuing of I/O completion events is enabled while jobs are pending.
+    /// `InProgress` (another asynchronous operation is being started) and
+    /// `Queue` (the completion event could not be queued) are reported as
+    /// failures of the `viReadAsync` operation, the read was not started.
+    pub fn read_async(&self, buffer: Vec<u8>) -> Result<AsyncReadJob<'_>> {
+        self.start_read_async(buffer, EventMechanism::Queue)
+    }
+
+    fn start_read_async(
+        &self,
+        mut buffer: Vec<u8>,
+        mechanism: EventMechanism,
+    ) -> Result<AsyncReadJob<'_>> {
+        let guard = self.use_io_completion(mechanism)?;
+
+        let mut job_id: ViJobId = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viReadAsync(
+                self.value,
+                buffer.as_mut_ptr(),
+                buffer.len() as ViUInt32,
+                &mut job_id,
+            ))
+        }
+        .map_err(|error| match error {
+            Error::InProgress | Error::Queue => error.in_operation("viReadAsync"),
+            error => error,
+        })?;
+        debug!(
+            "Asynchronous read {} started with completion code: {}",
+            job_id, completion_code
+        );
+        Ok(AsyncReadJob {
+            session: self,
+            job_id,
+            buffer: Some(buffer),
+            _mechanism: guard,
+        })
+    }
+
+    /// Enables I/O completion events for `mechanism` unless other jobs already
+    /// rely on it.
+    fn use_io_completion(&self, mechanism: EventMechanism) -> Result<MechanismGuard<'_>> {
+        let mut state = self.async_state();
+        let entry = state.mechanism(mechanism);
+        if entry.users == 0 {
+            let completion_code = self.enable_event_raw(EventType::IoCompletion, mechanism)?;
+            entry.enabled = completion_code != CompletionCode::EventEnabled;
+        }
+        entry.users += 1;
+        Ok(MechanismGuard {
+            session: self,
+            mechanism,
+        })
+    }
+
+    fn async_state(&self) -> MutexGuard<'_, AsyncState> {
+        self.async_state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Reads up to `len` bytes without blocking a thread, the completion is
+    /// delivered to the task through a tokio channel fed by an I/O completion
+    /// event handler.
+    ///
+    /// Several reads may be in flight on a session, the handler mechanism
+    /// stays enabled until the last one finished. Dropping the future before
+    /// completion terminates the read.
+    #[cfg(feature = "tokio")]
+    pub async fn read_async_tokio(&self, len: usize) -> Result<Vec<u8>> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let _handler = self.install_handler(EventType::IoCompletion, move |event| {
+            match JobCompletion::try_from(event) {
+                // The receiver is gone once the read finished or was dropped.
+                Ok(completion) => _ = sender.send(completion),
+                Err(error) => error!("Reading I/O completion event failed: {}", error),
+            }
+        })?;
+        let job = self.start_read_async(vec![0; len], EventMechanism::Handler)?;
+
+        while let Some(completion) = receiver.recv().await {
+            if completion.job_id == job.job_id {
+                let (mut buffer, count) = job.complete(completion)?;
+                buffer.truncate(count);
+                return Ok(buffer);
+            }
+        }
+        Err(Error::HandlerNotInstalled)
+    }
+}
@@ -1,4 +1,6 @@
-use visa_bindings::*;
+use crate::protocol::StandardEventStatusRegister;
+use alloc::{boxed::Box, string::String, vec::Vec};
+use status::*;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -199,7 +201,9 @@ pub enum Error {
     #[error("Invalid completion code supplied from VISA: {0}")]
     InvalidCompletionCode(u32),
     #[error("Invalid timeout value: {}", &0)]
-    InvalidTimeout(std::time::Duration),
+    InvalidTimeout(core::time::Duration),
+    #[error("Invalid break length, expected 1 to 500 ms: {0:?}")]
+    InvalidBreakLength(core::time::Duration),
     #[error("Write command wrote {length} bytes instead of {expected}")]
     WriteLengthMistmatch { length: usize, expected: usize },
     #[error("The buffer contains invalid UTF-8 characters")]
@@ -220,11 +224,100 @@ pub enum Error {
     SelfTestParse(String),
     #[error("Invalid Service Request Enable (*SRE?) response: {0}")]
     ServiceRequestEnableQueryParse(String),
+    #[error("Invalid Individual Status (*IST?) response: {0}")]
+    IndividualStatusQueryParse(String),
+    #[error("Invalid Parallel Poll Enable Register (*PRE?) response: {0}")]
+    ParallelPollEnableRegisterParse(String),
+    #[error("Invalid SCPI version (SYSTem:VERSion?) response: {0}")]
+    ScpiVersionParse(String),
+    #[error("Invalid numeric response: {0}")]
+    NumericParse(String),
+    #[error("The device is no longer present")]
+    DeviceNotPresent,
+    #[error("Failed to parse response {response:?} as {type_name}")]
+    ResponseParse {
+        response: String,
+        type_name: &'static str,
+    },
+    #[error("Template has {placeholders} placeholders but {arguments} arguments were given")]
+    TemplateArguments {
+        placeholders: usize,
+        arguments: usize,
+    },
+    #[error("Read did not complete within {0} reads")]
+    ReadLimitExceeded(usize),
+    #[error("Attribute value {0:#X} does not fit the requested type")]
+    AttributeOverflow(u64),
+    #[error("Failed to parse SCPI error queue entry: {0}")]
+    ScpiErrorParse(String),
+    #[error("Failed to match response: {0}")]
+    EnumParse(String),
+    #[error("Timeout expired after reading {} bytes", data.len())]
+    PartialRead { data: Vec<u8> },
+    #[error("Invalid IEEE 488.2 block header: {0}")]
+    InvalidBlockHeader(String),
+    #[error("Block contains {length} bytes instead of {expected}")]
+    IncompleteBlock { length: usize, expected: usize },
+    #[cfg(feature = "std")]
+    #[error("Reading the block data failed: {0}")]
+    BlockSource(std::io::Error),
+    #[error("Command rejected by the instrument: {0:?}")]
+    CommandRejected(StandardEventStatusRegister),
+    #[error("Transaction step {step} failed: {source}")]
+    TransactionFailed { step: usize, source: Box<Error> },
+    #[error("Transaction step {step} expected {expected:?} but read {actual:?}")]
+    TransactionMismatch {
+        step: usize,
+        expected: String,
+        actual: String,
+    },
+    #[error("Failed to release the session lock before closing: {0}")]
+    UnlockOnClose(Box<Error>),
+    #[error("Failed to open resource {resource}: {source}")]
+    OpenFailed {
+        resource: String,
+        source: Box<Error>,
+    },
+    #[error("{op} failed: {source}")]
+    Operation {
+        op: &'static str,
+        source: Box<Error>,
+    },
     #[error("Unexpected completion code: {0}")]
     UnexpectedCompletionCode(CompletionCode),
 }
 
-#[derive(Debug)]
+impl Error {
+    /// Wraps the error with the name of the operation that failed.
+    #[cfg(feature = "std")]
+    pub(crate) fn in_operation(self, op: &'static str) -> Self {
+        Self::Operation {
+            op,
+            source: Box::new(self),
+        }
+    }
+
+    /// Error underneath any `Operation` or `OpenFailed` wrappers.
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Self::Operation { source, .. } | Self::OpenFailed { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(value: Error) -> Self {
+        let kind = match value.root_cause() {
+            Error::Timeout => std::io::ErrorKind::TimedOut,
+            _ => std::io::ErrorKind::Other,
+        };
+        std::io::Error::new(kind, value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CompletionCode {
     Success,
     EventEnabled,
@@ -248,8 +341,8 @@ pub enum CompletionCode {
     ExtendedFunctionNotImplemented,
 }
 
-impl std::fmt::Display for CompletionCode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for CompletionCode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::Success => write!(f, "Operation completed successfully"),
             Self::EventEnabled => write!(
@@ -323,10 +416,118 @@ impl std::fmt::Display for CompletionCode {
     }
 }
 
-impl TryFrom<ViStatus> for CompletionCode {
+/// VISA status codes as defined by `visa.h`, kept here so the mapping to
+/// [`CompletionCode`] and [`Error`] also builds without the VISA bindings.
+pub(crate) mod status {
+    const ERROR: u32 = 0x8000_0000;
+
+    pub const VI_SUCCESS: i32 = 0;
+    pub const VI_SUCCESS_EVENT_EN: i32 = 0x3FFF_0002;
+    pub const VI_SUCCESS_EVENT_DIS: i32 = 0x3FFF_0003;
+    pub const VI_SUCCESS_QUEUE_EMPTY: i32 = 0x3FFF_0004;
+    pub const VI_SUCCESS_TERM_CHAR: i32 = 0x3FFF_0005;
+    pub const VI_SUCCESS_MAX_CNT: i32 = 0x3FFF_0006;
+    pub const VI_SUCCESS_DEV_NPRESENT: i32 = 0x3FFF_007D;
+    pub const VI_SUCCESS_TRIG_MAPPED: i32 = 0x3FFF_007E;
+    pub const VI_SUCCESS_QUEUE_NEMPTY: i32 = 0x3FFF_0080;
+    pub const VI_SUCCESS_NCHAIN: i32 = 0x3FFF_0098;
+    pub const VI_SUCCESS_NESTED_SHARED: i32 = 0x3FFF_0099;
+    pub const VI_SUCCESS_NESTED_EXCLUSIVE: i32 = 0x3FFF_009A;
+    pub const VI_SUCCESS_SYNC: i32 = 0x3FFF_009B;
+    pub const VI_WARN_QUEUE_OVERFLOW: i32 = 0x3FFF_000C;
+    pub const VI_WARN_CONFIG_NLOADED: i32 = 0x3FFF_0077;
+    pub const VI_WARN_NULL_OBJECT: i32 = 0x3FFF_0082;
+    pub const VI_WARN_NSUP_ATTR_STATE: i32 = 0x3FFF_0084;
+    pub const VI_WARN_UNKNOWN_STATUS: i32 = 0x3FFF_0085;
+    pub const VI_WARN_NSUP_BUF: i32 = 0x3FFF_0088;
+    pub const VI_WARN_EXT_FUNC_NIMPL: i32 = 0x3FFF_00A9;
+
+    pub const VI_ERROR_SYSTEM_ERROR: i32 = (ERROR + 0x3FFF_0000) as i32;
+    pub const VI_ERROR_INV_OBJECT: i32 = (ERROR + 0x3FFF_000E) as i32;
+    pub const VI_ERROR_RSRC_LOCKED: i32 = (ERROR + 0x3FFF_000F) as i32;
+    pub const VI_ERROR_INV_EXPR: i32 = (ERROR + 0x3FFF_0010) as i32;
+    pub const VI_ERROR_RSRC_NFOUND: i32 = (ERROR + 0x3FFF_0011) as i32;
+    pub const VI_ERROR_INV_RSRC_NAME: i32 = (ERROR + 0x3FFF_0012) as i32;
+    pub const VI_ERROR_INV_ACC_MODE: i32 = (ERROR + 0x3FFF_0013) as i32;
+    pub const VI_ERROR_TMO: i32 = (ERROR + 0x3FFF_0015) as i32;
+    pub const VI_ERROR_CLOSING_FAILED: i32 = (ERROR + 0x3FFF_0016) as i32;
+    pub const VI_ERROR_INV_DEGREE: i32 = (ERROR + 0x3FFF_001B) as i32;
+    pub const VI_ERROR_INV_JOB_ID: i32 = (ERROR + 0x3FFF_001C) as i32;
+    pub const VI_ERROR_NSUP_ATTR: i32 = (ERROR + 0x3FFF_001D) as i32;
+    pub const VI_ERROR_NSUP_ATTR_STATE: i32 = (ERROR + 0x3FFF_001E) as i32;
+    pub const VI_ERROR_ATTR_READONLY: i32 = (ERROR + 0x3FFF_001F) as i32;
+    pub const VI_ERROR_INV_LOCK_TYPE: i32 = (ERROR + 0x3FFF_0020) as i32;
+    pub const VI_ERROR_INV_ACCESS_KEY: i32 = (ERROR + 0x3FFF_0021) as i32;
+    pub const VI_ERROR_INV_EVENT: i32 = (ERROR + 0x3FFF_0026) as i32;
+    pub const VI_ERROR_INV_MECH: i32 = (ERROR + 0x3FFF_0027) as i32;
+    pub const VI_ERROR_HNDLR_NINSTALLED: i32 = (ERROR + 0x3FFF_0028) as i32;
+    pub const VI_ERROR_INV_HNDLR_REF: i32 = (ERROR + 0x3FFF_0029) as i32;
+    pub const VI_ERROR_INV_CONTEXT: i32 = (ERROR + 0x3FFF_002A) as i32;
+    pub const VI_ERROR_QUEUE_OVERFLOW: i32 = (ERROR + 0x3FFF_002D) as i32;
+    pub const VI_ERROR_NENABLED: i32 = (ERROR + 0x3FFF_002F) as i32;
+    pub const VI_ERROR_ABORT: i32 = (ERROR + 0x3FFF_0030) as i32;
+    pub const VI_ERROR_RAW_WR_PROT_VIOL: i32 = (ERROR + 0x3FFF_0034) as i32;
+    pub const VI_ERROR_RAW_RD_PROT_VIOL: i32 = (ERROR + 0x3FFF_0035) as i32;
+    pub const VI_ERROR_OUTP_PROT_VIOL: i32 = (ERROR + 0x3FFF_0036) as i32;
+    pub const VI_ERROR_INP_PROT_VIOL: i32 = (ERROR + 0x3FFF_0037) as i32;
+    pub const VI_ERROR_BERR: i32 = (ERROR + 0x3FFF_0038) as i32;
+    pub const VI_ERROR_IN_PROGRESS: i32 = (ERROR + 0x3FFF_0039) as i32;
+    pub const VI_ERROR_INV_SETUP: i32 = (ERROR + 0x3FFF_003A) as i32;
+    pub const VI_ERROR_QUEUE_ERROR: i32 = (ERROR + 0x3FFF_003B) as i32;
+    pub const VI_ERROR_ALLOC: i32 = (ERROR + 0x3FFF_003C) as i32;
+    pub const VI_ERROR_INV_MASK: i32 = (ERROR + 0x3FFF_003D) as i32;
+    pub const VI_ERROR_IO: i32 = (ERROR + 0x3FFF_003E) as i32;
+    pub const VI_ERROR_INV_FMT: i32 = (ERROR + 0x3FFF_003F) as i32;
+    pub const VI_ERROR_NSUP_FMT: i32 = (ERROR + 0x3FFF_0041) as i32;
+    pub const VI_ERROR_LINE_IN_USE: i32 = (ERROR + 0x3FFF_0042) as i32;
+    pub const VI_ERROR_NSUP_MODE: i32 = (ERROR + 0x3FFF_0046) as i32;
+    pub const VI_ERROR_SRQ_NOCCURRED: i32 = (ERROR + 0x3FFF_004A) as i32;
+    pub const VI_ERROR_INV_SPACE: i32 = (ERROR + 0x3FFF_004E) as i32;
+    pub const VI_ERROR_INV_OFFSET: i32 = (ERROR + 0x3FFF_0051) as i32;
+    pub const VI_ERROR_INV_WIDTH: i32 = (ERROR + 0x3FFF_0052) as i32;
+    pub const VI_ERROR_NSUP_OFFSET: i32 = (ERROR + 0x3FFF_0054) as i32;
+    pub const VI_ERROR_NSUP_VAR_WIDTH: i32 = (ERROR + 0x3FFF_0055) as i32;
+    pub const VI_ERROR_WINDOW_NMAPPED: i32 = (ERROR + 0x3FFF_0057) as i32;
+    pub const VI_ERROR_RESP_PENDING: i32 = (ERROR + 0x3FFF_0059) as i32;
+    pub const VI_ERROR_NLISTENERS: i32 = (ERROR + 0x3FFF_005F) as i32;
+    pub const VI_ERROR_NCIC: i32 = (ERROR + 0x3FFF_0060) as i32;
+    pub const VI_ERROR_NSYS_CNTLR: i32 = (ERROR + 0x3FFF_0061) as i32;
+    pub const VI_ERROR_NSUP_OPER: i32 = (ERROR + 0x3FFF_0067) as i32;
+    pub const VI_ERROR_INTR_PENDING: i32 = (ERROR + 0x3FFF_0068) as i32;
+    pub const VI_ERROR_ASRL_PARITY: i32 = (ERROR + 0x3FFF_006A) as i32;
+    pub const VI_ERROR_ASRL_FRAMING: i32 = (ERROR + 0x3FFF_006B) as i32;
+    pub const VI_ERROR_ASRL_OVERRUN: i32 = (ERROR + 0x3FFF_006C) as i32;
+    pub const VI_ERROR_TRIG_NMAPPED: i32 = (ERROR + 0x3FFF_006E) as i32;
+    pub const VI_ERROR_NSUP_ALIGN_OFFSET: i32 = (ERROR + 0x3FFF_0070) as i32;
+    pub const VI_ERROR_USER_BUF: i32 = (ERROR + 0x3FFF_0071) as i32;
+    pub const VI_ERROR_RSRC_BUSY: i32 = (ERROR + 0x3FFF_0072) as i32;
+    pub const VI_ERROR_NSUP_WIDTH: i32 = (ERROR + 0x3FFF_0076) as i32;
+    pub const VI_ERROR_INV_PARAMETER: i32 = (ERROR + 0x3FFF_0078) as i32;
+    pub const VI_ERROR_INV_PROT: i32 = (ERROR + 0x3FFF_0079) as i32;
+    pub const VI_ERROR_INV_SIZE: i32 = (ERROR + 0x3FFF_007B) as i32;
+    pub const VI_ERROR_WINDOW_MAPPED: i32 = (ERROR + 0x3FFF_0080) as i32;
+    pub const VI_ERROR_NIMPL_OPER: i32 = (ERROR + 0x3FFF_0081) as i32;
+    pub const VI_ERROR_INV_LENGTH: i32 = (ERROR + 0x3FFF_0083) as i32;
+    pub const VI_ERROR_INV_MODE: i32 = (ERROR + 0x3FFF_0091) as i32;
+    pub const VI_ERROR_SESN_NLOCKED: i32 = (ERROR + 0x3FFF_009C) as i32;
+    pub const VI_ERROR_MEM_NSHARED: i32 = (ERROR + 0x3FFF_009D) as i32;
+    pub const VI_ERROR_LIBRARY_NFOUND: i32 = (ERROR + 0x3FFF_009E) as i32;
+    pub const VI_ERROR_NSUP_INTR: i32 = (ERROR + 0x3FFF_009F) as i32;
+    pub const VI_ERROR_INV_LINE: i32 = (ERROR + 0x3FFF_00A0) as i32;
+    pub const VI_ERROR_FILE_ACCESS: i32 = (ERROR + 0x3FFF_00A1) as i32;
+    pub const VI_ERROR_FILE_IO: i32 = (ERROR + 0x3FFF_00A2) as i32;
+    pub const VI_ERROR_NSUP_LINE: i32 = (ERROR + 0x3FFF_00A3) as i32;
+    pub const VI_ERROR_NSUP_MECH: i32 = (ERROR + 0x3FFF_00A4) as i32;
+    pub const VI_ERROR_INTF_NUM_NCONFIG: i32 = (ERROR + 0x3FFF_00A5) as i32;
+    pub const VI_ERROR_CONN_LOST: i32 = (ERROR + 0x3FFF_00A6) as i32;
+    pub const VI_ERROR_MACHINE_NAVAIL: i32 = (ERROR + 0x3FFF_00A7) as i32;
+    pub const VI_ERROR_NPERMISSION: i32 = (ERROR + 0x3FFF_00A8) as i32;
+}
+
+impl TryFrom<i32> for CompletionCode {
     type Error = Error;
 
-    fn try_from(value: ViStatus) -> std::result::Result<Self, Self::Error> {
+    fn try_from(value: i32) -> Result<Self> {
         match value {
             VI_ERROR_SYSTEM_ERROR => Err(Self::Error::System),
             VI_ERROR_INV_OBJECT => Err(Self::Error::InvalidObject),
@@ -408,34 +609,28 @@ impl TryFrom<ViStatus> for CompletionCode {
             VI_ERROR_CONN_LOST => Err(Self::Error::ConnectionLost),
             VI_ERROR_MACHINE_NAVAIL => Err(Self::Error::MachineNotAvailable),
             VI_ERROR_NPERMISSION => Err(Self::Error::NoPermission),
-            other => {
-                if other < 0 {
-                    return Err(Self::Error::InvalidErrorCode(other));
-                }
-                match other as u32 {
-                    VI_SUCCESS => Ok(Self::Success),
-                    VI_SUCCESS_EVENT_EN => Ok(Self::EventEnabled),
-                    VI_SUCCESS_EVENT_DIS => Ok(Self::EventDisabled),
-                    VI_SUCCESS_QUEUE_EMPTY => Ok(Self::QueueEmpty),
-                    VI_SUCCESS_TERM_CHAR => Ok(Self::TerminationCharacterRead),
-                    VI_SUCCESS_MAX_CNT => Ok(Self::MaximumCount),
-                    VI_SUCCESS_DEV_NPRESENT => Ok(Self::DeviceNotPresent),
-                    VI_SUCCESS_TRIG_MAPPED => Ok(Self::TrigPathMapped),
-                    VI_SUCCESS_QUEUE_NEMPTY => Ok(Self::QueueNotEmpty),
-                    VI_SUCCESS_NCHAIN => Ok(Self::DoNotInvokeHandler),
-                    VI_SUCCESS_NESTED_SHARED => Ok(Self::NestedSharedLock),
-                    VI_SUCCESS_NESTED_EXCLUSIVE => Ok(Self::NestedExclusiveLock),
-                    VI_SUCCESS_SYNC => Ok(Self::AsynchronousOperationHandledSynchronously),
-                    VI_WARN_QUEUE_OVERFLOW => Ok(Self::QueueOverflow),
-                    VI_WARN_CONFIG_NLOADED => Ok(Self::ConfigurationNotLoaded),
-                    VI_WARN_NULL_OBJECT => Ok(Self::NullObject),
-                    VI_WARN_NSUP_ATTR_STATE => Ok(Self::AttributeStateNotSupported),
-                    VI_WARN_UNKNOWN_STATUS => Ok(Self::UnknownStatus),
-                    VI_WARN_NSUP_BUF => Ok(Self::BufferNotSupported),
-                    VI_WARN_EXT_FUNC_NIMPL => Ok(Self::ExtendedFunctionNotImplemented),
-                    other => Err(Error::InvalidCompletionCode(other)),
-                }
-            }
+            VI_SUCCESS => Ok(Self::Success),
+            VI_SUCCESS_EVENT_EN => Ok(Self::EventEnabled),
+            VI_SUCCESS_EVENT_DIS => Ok(Self::EventDisabled),
+            VI_SUCCESS_QUEUE_EMPTY => Ok(Self::QueueEmpty),
+            VI_SUCCESS_TERM_CHAR => Ok(Self::TerminationCharacterRead),
+            VI_SUCCESS_MAX_CNT => Ok(Self::MaximumCount),
+            VI_SUCCESS_DEV_NPRESENT => Ok(Self::DeviceNotPresent),
+            VI_SUCCESS_TRIG_MAPPED => Ok(Self::TrigPathMapped),
+            VI_SUCCESS_QUEUE_NEMPTY => Ok(Self::QueueNotEmpty),
+            VI_SUCCESS_NCHAIN => Ok(Self::DoNotInvokeHandler),
+            VI_SUCCESS_NESTED_SHARED => Ok(Self::NestedSharedLock),
+            VI_SUCCESS_NESTED_EXCLUSIVE => Ok(Self::NestedExclusiveLock),
+            VI_SUCCESS_SYNC => Ok(Self::AsynchronousOperationHandledSynchronously),
+            VI_WARN_QUEUE_OVERFLOW => Ok(Self::QueueOverflow),
+            VI_WARN_CONFIG_NLOADED => Ok(Self::ConfigurationNotLoaded),
+            VI_WARN_NULL_OBJECT => Ok(Self::NullObject),
+            VI_WARN_NSUP_ATTR_STATE => Ok(Self::AttributeStateNotSupported),
+            VI_WARN_UNKNOWN_STATUS => Ok(Self::UnknownStatus),
+            VI_WARN_NSUP_BUF => Ok(Self::BufferNotSupported),
+            VI_WARN_EXT_FUNC_NIMPL => Ok(Self::ExtendedFunctionNotImplemented),
+            other if other < 0 => Err(Self::Error::InvalidErrorCode(other)),
+            other => Err(Error::InvalidCompletionCode(other as u32)),
         }
     }
 }
@@ -0,0 +1,353 @@
+use crate::{
+    error::*,
+    session::{Session, narrow_attribute},
+    utility::Timeout,
+};
+use std::{
+    fmt,
+    mem::ManuallyDrop,
+    panic::{AssertUnwindSafe, catch_unwind},
+};
+use tracing::{debug, error};
+use visa_bindings::*;
+
+type Handler = Box<dyn Fn(&Event) + Send + Sync>;
+
+/// Closure a VISA handler callback is dispatched to, passed to VISA as the
+/// user handle.
+///
+/// It deliberately holds no reference to the session: a forgotten
+/// [`HandlerToken`] stays installed after the session moved, until VISA
+/// uninstalls it when closing the session, and the data is then leaked.
+struct HandlerData {
+    handler: Handler,
+}
+
+/// Event handler installed with [`Session::install_handler`], uninstalled on
+/// drop.
+pub struct HandlerToken<'a> {
+    session: &'a Session,
+    event: EventType,
+    data: *mut HandlerData,
+    installed: bool,
+}
+
+// SAFETY: the handler data only holds the `Send + Sync` closure, which is
+// shared with the VISA callback thread anyway.
+unsafe impl Send for HandlerToken<'_> {}
+
+impl fmt::Debug for HandlerToken<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandlerToken")
+            .field("event", &self.event)
+            .field("installed", &self.installed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for HandlerToken<'_> {
+    fn drop(&mut self) {
+        if !self.installed {
+            return;
+        }
+        if let Err(error) = self.uninstall_raw() {
+            error!("Uninstalling event handler failed with code: {}", error);
+        }
+    }
+}
+
+impl HandlerToken<'_> {
+    pub fn uninstall_handler(mut self) -> Result<()> {
+        self.uninstall_raw()
+    }
+
+    fn uninstall_raw(&mut self) -> Result<()> {
+        self.installed = false;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viUninstallHandler(
+                self.session.value,
+                self.event.into(),
+                Some(handler_trampoline),
+                self.data as ViAddr,
+            ))?
+        };
+        debug!(
+            "Event handler uninstalled with completion code: {}",
+            completion_code
+        );
+        // VISA no longer calls the handler, the closure can be freed. It is
+        // leaked instead if uninstalling failed.
+        drop(unsafe { Box::from_raw(self.data) });
+        Ok(())
+    }
+}
+
+unsafe extern "C" fn handler_trampoline(
+    _session: ViSession,
+    event_type: ViEventType,
+    context: ViEvent,
+    user_handle: ViAddr,
+) -> ViStatus {
+    // The handle points to `HandlerData` freed only once the handler is
+    // uninstalled, or leaked if its token was forgotten.
+    let data = unsafe { &*(user_handle as *const HandlerData) };
+    let Ok(event_type) = EventType::try_from(event_type) else {
+        return VI_SUCCESS as ViStatus;
+    };
+    // VISA closes the context once the handler returns.
+    let event = ManuallyDrop::new(Event {
+        value: context,
+        event_type,
+    });
+
+    if catch_unwind(AssertUnwindSafe(|| (data.handler)(&event))).is_err() {
+        error!("Event handler for {:?} panicked", event_type);
+    }
+    VI_SUCCESS as ViStatus
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventType {
+    ServiceRequest,
+    IoCompletion,
+    Exception,
+    Trigger,
+    Clear,
+    GpibControllerInCharge,
+    GpibTalk,
+    GpibListen,
+}
+
+impl From<EventType> for ViEventType {
+    fn from(value: EventType) -> Self {
+        match value {
+            EventType::ServiceRequest => VI_EVENT_SERVICE_REQ,
+            EventType::IoCompletion => VI_EVENT_IO_COMPLETION,
+            EventType::Exception => VI_EVENT_EXCEPTION,
+            EventType::Trigger => VI_EVENT_TRIG,
+            EventType::Clear => VI_EVENT_CLEAR,
+            EventType::GpibControllerInCharge => VI_EVENT_GPIB_CIC,
+            EventType::GpibTalk => VI_EVENT_GPIB_TALK,
+            EventType::GpibListen => VI_EVENT_GPIB_LISTEN,
+        }
+    }
+}
+
+impl TryFrom<ViEventType> for EventType {
+    type Error = Error;
+    fn try_from(value: ViEventType) -> Result<Self> {
+        match value {
+            VI_EVENT_SERVICE_REQ => Ok(EventType::ServiceRequest),
+            VI_EVENT_IO_COMPLETION => Ok(EventType::IoCompletion),
+            VI_EVENT_EXCEPTION => Ok(EventType::Exception),
+            VI_EVENT_TRIG => Ok(EventType::Trigger),
+            VI_EVENT_CLEAR => Ok(EventType::Clear),
+            VI_EVENT_GPIB_CIC => Ok(EventType::GpibControllerInCharge),
+            VI_EVENT_GPIB_TALK => Ok(EventType::GpibTalk),
+            VI_EVENT_GPIB_LISTEN => Ok(EventType::GpibListen),
+            _ => Err(Error::InvalidEvent),
+        }
+    }
+}
+
+/// How VISA delivers an enabled event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventMechanism {
+    /// Events are queued for [`Session::wait_on_event`].
+    Queue,
+    /// Events invoke the installed handler.
+    Handler,
+    /// Events are held until the handler mechanism is enabled.
+    Suspend,
+}
+
+impl From<EventMechanism> for ViUInt16 {
+    fn from(value: EventMechanism) -> Self {
+        let value = match value {
+            EventMechanism::Queue => VI_QUEUE,
+            EventMechanism::Handler => VI_HNDLR,
+            EventMechanism::Suspend => VI_SUSPEND_HNDLR,
+        };
+        value as ViUInt16
+    }
+}
+
+/// Event context received from VISA, closed on drop.
+#[derive(Debug)]
+pub struct Event {
+    value: ViEvent,
+    event_type: EventType,
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        let result = unsafe { CompletionCode::try_from(viClose(self.value)) };
+        match result {
+            Ok(completion_code) => debug!(
+                "Event context was closed with completion code: {}",
+                completion_code
+            ),
+            Err(error) => error!("Closing event context failed with code: {}", error),
+        }
+    }
+}
+
+impl Event {
+    pub fn event_type(&self) -> EventType {
+        self.event_type
+    }
+
+    pub(crate) fn get_attribute<T: TryFrom<ViAttrState>>(&self, attribute: ViAttr) -> Result<T> {
+        let mut value: ViAttrState = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viGetAttribute(
+                self.value,
+                attribute,
+                &mut value as *mut ViAttrState as _,
+            ))?
+        };
+        debug!(
+            "Event attribute {:#X} read with completion code: {}",
+            attribute, completion_code
+        );
+        narrow_attribute(value)
+    }
+}
+
+impl Session {
+    pub fn enable_event(&self, event: EventType, mechanism: EventMechanism) -> Result<()> {
+        self.enable_event_raw(event, mechanism)?;
+        Ok(())
+    }
+
+    pub fn disable_event(&self, event: EventType, mechanism: EventMechanism) -> Result<()> {
+        let completion_code = unsafe {
+            CompletionCode::try_from(viDisableEvent(self.value, event.into(), mechanism.into()))?
+        };
+        debug!(
+            "Event {:?} disabled with completion code: {}",
+            event, completion_code
+        );
+        Ok(())
+    }
+
+    /// Installs `handler` to be called from the VISA callback thread for
+    /// `event`, the event must also be enabled with
+    /// [`EventMechanism::Handler`].
+    ///
+    /// The handler may run concurrently with the caller, hence `Sync`. It
+    /// only receives the event, a handler acting on the session should signal
+    /// the owner of the session instead, through a channel for example.
+    pub fn install_handler<F>(&self, event: EventType, handler: F) -> Result<HandlerToken<'_>>
+    where
+        F: Fn(&Event) + Send + Sync + 'static,
+    {
+        let data = Box::into_raw(Box::new(HandlerData {
+            handler: Box::new(handler),
+        }));
+        let result = unsafe {
+            CompletionCode::try_from(viInstallHandler(
+                self.value,
+                event.into(),
+                Some(handler_trampoline),
+                data as ViAddr,
+            ))
+        };
+        let completion_code = match result {
+            Ok(completion_code) => completion_code,
+            Err(error) => {
+                drop(unsafe { Box::from_raw(data) });
+                return Err(error);
+            }
+        };
+        debug!(
+            "Event handler installed with completion code: {}",
+            completion_code
+        );
+
+        Ok(HandlerToken {
+            session: self,
+            event,
+            data,
+            installed: true,
+        })
+    }
+
+    /// Waits for `event`, which must be enabled with [`EventMechanism::Queue`].
+    pub fn wait_on_event(&self, event: EventType, timeout: Timeout) -> Result<Event> {
+        self.wait_on(event.into(), timeout)
+    }
+
+    /// Waits for the first of `events` to occur, using the queue mechanism.
+    ///
+    /// Events that were not enabled before the call are disabled again
+    /// afterwards. Other events already enabled for queuing on this session
+    /// may also end the wait.
+    pub fn wait_on_any_event(&self, events: &[EventType], timeout: Timeout) -> Result<Event> {
+        let mut enabled = vec![];
+        let result = events
+            .iter()
+            .try_for_each(|event| {
+                let completion_code = self.enable_event_raw(*event, EventMechanism::Queue)?;
+                if completion_code != CompletionCode::EventEnabled {
+                    enabled.push(*event);
+                }
+                Ok(())
+            })
+            .and_then(|_| self.wait_on(VI_ALL_ENABLED_EVENTS, timeout));
+
+        for event in enabled {
+            if let Err(error) = self.disable_event(event, EventMechanism::Queue) {
+                error!("Disabling event {:?} failed: {}", event, error);
+            }
+        }
+
+        result
+    }
+
+    pub(crate) fn enable_event_raw(
+        &self,
+        event: EventType,
+        mechanism: EventMechanism,
+    ) -> Result<CompletionCode> {
+        let completion_code = unsafe {
+            CompletionCode::try_from(viEnableEvent(
+                self.value,
+                event.into(),
+                mechanism.into(),
+                VI_NULL as ViEventFilter,
+            ))?
+        };
+        debug!(
+            "Event {:?} enabled with completion code: {}",
+            event, completion_code
+        );
+        Ok(completion_code)
+    }
+
+    fn wait_on(&self, event: ViEventType, timeout: Timeout) -> Result<Event> {
+        let mut event_type: ViEventType = 0;
+        let mut context: ViEvent = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viWaitOnEvent(
+                self.value,
+                event,
+                timeout.try_into()?,
+                &mut event_type,
+                &mut context,
+            ))?
+        };
+        debug!("Wait on event completed with code: {}", completion_code);
+
+        match EventType::try_from(event_type) {
+            Ok(event_type) => Ok(Event {
+                value: context,
+                event_type,
+            }),
+            Err(error) => {
+                unsafe { viClose(context) };
+                Err(error)
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{error::*, session::Session, utility::TriggerProtocol};
+use tracing::debug;
+use visa_bindings::*;
+
+/// Remote enable line action performed by `viGpibControlREN`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenMode {
+    /// Deasserts REN.
+    Deassert,
+    /// Asserts REN.
+    Assert,
+    /// Sends Go To Local to the device and deasserts REN.
+    DeassertGtl,
+    /// Asserts REN and addresses the device.
+    AssertAddress,
+    /// Sends Local Lockout to all devices, asserting REN first.
+    AssertLlo,
+    /// Asserts REN, addresses the device and sends Local Lockout.
+    AssertAddressLlo,
+    /// Sends Go To Local to the device.
+    AddressGtl,
+}
+
+impl From<RenMode> for ViUInt16 {
+    fn from(value: RenMode) -> Self {
+        let value = match value {
+            RenMode::Deassert => VI_GPIB_REN_DEASSERT,
+            RenMode::Assert => VI_GPIB_REN_ASSERT,
+            RenMode::DeassertGtl => VI_GPIB_REN_DEASSERT_GTL,
+            RenMode::AssertAddress => VI_GPIB_REN_ASSERT_ADDRESS,
+            RenMode::AssertLlo => VI_GPIB_REN_ASSERT_LLO,
+            RenMode::AssertAddressLlo => VI_GPIB_REN_ASSERT_ADDRESS_LLO,
+            RenMode::AddressGtl => VI_GPIB_REN_ADDRESS_GTL,
+        };
+        value as ViUInt16
+    }
+}
+
+impl Session {
+    /// Controls the GPIB remote enable line, for example to put an instrument
+    /// into local lockout or return it to local control.
+    ///
+    /// Fails with `OperationNotSupported` on non GPIB sessions.
+    pub fn gpib_control_ren(&self, mode: RenMode) -> Result<()> {
+        let completion_code =
+            unsafe { CompletionCode::try_from(viGpibControlREN(self.value, mode.into()))? };
+        debug!("GPIB REN control completed with code: {}", completion_code);
+        Ok(())
+    }
+
+    /// Pulses the GPIB interface clear line, making this controller the CIC.
+    ///
+    /// Only valid on GPIB interface (`GPIB<n>::INTFC`) sessions.
+    pub fn gpib_send_ifc(&self) -> Result<()> {
+        let completion_code = unsafe { CompletionCode::try_from(viGpibSendIFC(self.value))? };
+        debug!(
+            "Interface clear sent with completion code: {}",
+            completion_code
+        );
+        Ok(())
+    }
+
+    /// Sends a bus or hardware trigger instead of the `*TRG` command.
+    ///
+    /// Fails with `TriggerLineInUse` if another session holds the line.
+    pub fn assert_trigger(&self, protocol: TriggerProtocol) -> Result<()> {
+        let completion_code =
+            unsafe { CompletionCode::try_from(viAssertTrigger(self.value, protocol.into()))? };
+        debug!("Trigger asserted with completion code: {}", completion_code);
+        Ok(())
+    }
+}
@@ -1,6 +1,27 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod async_io;
 pub mod error;
+#[cfg(feature = "std")]
+pub mod event;
+#[cfg(feature = "std")]
+pub mod gpib;
+#[cfg(feature = "std")]
+pub mod memory;
+pub mod protocol;
+#[cfg(feature = "std")]
 pub mod resource_manager;
+pub mod resource_name;
+#[cfg(feature = "std")]
 pub mod session;
-#[cfg(test)]
+#[cfg(feature = "std")]
+pub mod session_cache;
+#[cfg(feature = "std")]
+pub mod socket_session;
+#[cfg(all(test, feature = "std"))]
 mod test;
+#[cfg(feature = "std")]
 pub mod utility;
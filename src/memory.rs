@@ -0,0 +1,294 @@
+use crate::{error::*, session::Session};
+use tracing::{debug, error};
+use visa_bindings::*;
+
+/// Address space of a register based (VXI, PXI) device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressSpace {
+    A16,
+    A24,
+    A32,
+    A64,
+}
+
+impl From<AddressSpace> for ViUInt16 {
+    fn from(value: AddressSpace) -> Self {
+        let value = match value {
+            AddressSpace::A16 => VI_A16_SPACE,
+            AddressSpace::A24 => VI_A24_SPACE,
+            AddressSpace::A32 => VI_A32_SPACE,
+            AddressSpace::A64 => VI_A64_SPACE,
+        };
+        value as ViUInt16
+    }
+}
+
+/// Block of device shared memory allocated with `viMemAlloc`, freed on drop.
+#[derive(Debug)]
+pub struct MemHandle<'a> {
+    session: &'a Session,
+    offset: ViBusAddress,
+    size: usize,
+}
+
+impl Drop for MemHandle<'_> {
+    fn drop(&mut self) {
+        let result =
+            unsafe { CompletionCode::try_from(viMemFree(self.session.value, self.offset)) };
+        match result {
+            Ok(completion_code) => debug!(
+                "Shared memory at {:#X} was freed with completion code: {}",
+                self.offset, completion_code
+            ),
+            Err(error) => error!("Freeing shared memory failed with code: {}", error),
+        }
+    }
+}
+
+impl MemHandle<'_> {
+    /// Offset of the block in the device's shared memory space.
+    pub fn offset(&self) -> ViBusAddress {
+        self.offset
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+/// Window of a device address space mapped with `viMapAddress`, unmapped on
+/// drop.
+///
+/// A session maps at most one window at a time, mapping another one fails
+/// with `WindowMapped` while this one is alive.
+#[derive(Debug)]
+pub struct MappedWindow<'a> {
+    session: &'a Session,
+    address: ViAddr,
+    size: usize,
+}
+
+impl Drop for MappedWindow<'_> {
+    fn drop(&mut self) {
+        let result = unsafe { CompletionCode::try_from(viUnmapAddress(self.session.value)) };
+        match result {
+            Ok(completion_code) => debug!(
+                "Address window was unmapped with completion code: {}",
+                completion_code
+            ),
+            Err(error) => error!("Unmapping address window failed with code: {}", error),
+        }
+    }
+}
+
+impl MappedWindow<'_> {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Reads the 32-bit value at `offset` into the window (`viPeek32`).
+    ///
+    /// Fails with `InvalidOffset` if the value does not lie within the
+    /// window.
+    pub fn peek32(&self, offset: usize) -> Result<u32> {
+        let address = self.address_of(offset)?;
+        let mut value: ViUInt32 = 0;
+        let completion_code =
+            unsafe { CompletionCode::try_from(viPeek32(self.session.value, address, &mut value))? };
+        debug!("Peek completion code: {}", completion_code);
+        Ok(value)
+    }
+
+    /// Writes the 32-bit value at `offset` into the window (`viPoke32`).
+    pub fn poke32(&self, offset: usize, value: u32) -> Result<()> {
+        let address = self.address_of(offset)?;
+        let completion_code =
+            unsafe { CompletionCode::try_from(viPoke32(self.session.value, address, value))? };
+        debug!("Poke completion code: {}", completion_code);
+        Ok(())
+    }
+
+    fn address_of(&self, offset: usize) -> Result<ViAddr> {
+        match offset.checked_add(size_of::<u32>()) {
+            Some(end) if end <= self.size => {
+                // The mapped address belongs to VISA, not to an allocation of
+                // this process, so only compute it without dereferencing.
+                Ok(self.address.cast::<u8>().wrapping_add(offset).cast())
+            }
+            _ => Err(Error::InvalidOffset),
+        }
+    }
+}
+
+impl Session {
+    /// Allocates `size` bytes of the device's shared memory.
+    ///
+    /// Fails with `MemoryNotShared` if the device exports no shared memory
+    /// and with `Allocation` if not enough of it is free.
+    pub fn mem_alloc(&self, size: usize) -> Result<MemHandle<'_>> {
+        let mut offset: ViBusAddress = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viMemAlloc(self.value, size as ViBusSize, &mut offset))?
+        };
+        debug!(
+            "Shared memory allocated with completion code: {}",
+            completion_code
+        );
+        Ok(MemHandle {
+            session: self,
+            offset,
+            size,
+        })
+    }
+
+    /// Reads an 8-bit register (`viIn8`).
+    ///
+    /// Fails with `InvalidOffset` or `InvalidWidth` if the device does not
+    /// allow the access at `offset`.
+    pub fn read_register8(&self, space: AddressSpace, offset: u64) -> Result<u8> {
+        let mut value: ViUInt8 = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viIn8(self.value, space.into(), offset, &mut value))?
+        };
+        debug!(
+            "Register {:?} {:#X} read with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(value)
+    }
+
+    /// Reads a 16-bit register (`viIn16`).
+    pub fn read_register16(&self, space: AddressSpace, offset: u64) -> Result<u16> {
+        let mut value: ViUInt16 = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viIn16(self.value, space.into(), offset, &mut value))?
+        };
+        debug!(
+            "Register {:?} {:#X} read with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(value)
+    }
+
+    /// Reads a 32-bit register (`viIn32`).
+    pub fn read_register32(&self, space: AddressSpace, offset: u64) -> Result<u32> {
+        let mut value: ViUInt32 = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viIn32(self.value, space.into(), offset, &mut value))?
+        };
+        debug!(
+            "Register {:?} {:#X} read with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(value)
+    }
+
+    /// Writes an 8-bit register (`viOut8`).
+    pub fn write_register8(&self, space: AddressSpace, offset: u64, value: u8) -> Result<()> {
+        let completion_code =
+            unsafe { CompletionCode::try_from(viOut8(self.value, space.into(), offset, value))? };
+        debug!(
+            "Register {:?} {:#X} written with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(())
+    }
+
+    /// Writes a 16-bit register (`viOut16`).
+    pub fn write_register16(&self, space: AddressSpace, offset: u64, value: u16) -> Result<()> {
+        let completion_code =
+            unsafe { CompletionCode::try_from(viOut16(self.value, space.into(), offset, value))? };
+        debug!(
+            "Register {:?} {:#X} written with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(())
+    }
+
+    /// Writes a 32-bit register (`viOut32`).
+    pub fn write_register32(&self, space: AddressSpace, offset: u64, value: u32) -> Result<()> {
+        let completion_code =
+            unsafe { CompletionCode::try_from(viOut32(self.value, space.into(), offset, value))? };
+        debug!(
+            "Register {:?} {:#X} written with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(())
+    }
+
+    /// Reads `count` consecutive 32-bit values starting at `offset`
+    /// (`viMoveIn32`), for example to drain a FIFO window.
+    pub fn move_in32(&self, space: AddressSpace, offset: u64, count: usize) -> Result<Vec<u32>> {
+        let mut data = vec![0 as ViUInt32; count];
+        let completion_code = unsafe {
+            CompletionCode::try_from(viMoveIn32(
+                self.value,
+                space.into(),
+                offset,
+                count as ViBusSize,
+                data.as_mut_ptr(),
+            ))?
+        };
+        debug!(
+            "{} values moved in from {:?} {:#X} with completion code: {}",
+            count, space, offset, completion_code
+        );
+        Ok(data)
+    }
+
+    /// Writes `data` as consecutive 32-bit values starting at `offset`
+    /// (`viMoveOut32`).
+    pub fn move_out32(&self, space: AddressSpace, offset: u64, data: &[u32]) -> Result<()> {
+        let completion_code = unsafe {
+            CompletionCode::try_from(viMoveOut32(
+                self.value,
+                space.into(),
+                offset,
+                data.len() as ViBusSize,
+                data.as_ptr() as _,
+            ))?
+        };
+        debug!(
+            "{} values moved out to {:?} {:#X} with completion code: {}",
+            data.len(),
+            space,
+            offset,
+            completion_code
+        );
+        Ok(())
+    }
+
+    /// Maps `size` bytes of `space` starting at `offset` into the process
+    /// (`viMapAddress`) for [`MappedWindow::peek32`] and
+    /// [`MappedWindow::poke32`] access.
+    ///
+    /// Fails with `WindowMapped` if the session already has a window mapped.
+    pub fn map_address(
+        &self,
+        space: AddressSpace,
+        offset: u64,
+        size: usize,
+    ) -> Result<MappedWindow<'_>> {
+        let mut address: ViAddr = std::ptr::null_mut();
+        let completion_code = unsafe {
+            CompletionCode::try_from(viMapAddress(
+                self.value,
+                space.into(),
+                offset,
+                size as ViBusSize,
+                VI_FALSE as ViBoolean,
+                VI_NULL as ViAddr,
+                &mut address,
+            ))?
+        };
+        debug!(
+            "Address window {:?} {:#X} mapped with completion code: {}",
+            space, offset, completion_code
+        );
+        Ok(MappedWindow {
+            session: self,
+            address,
+            size,
+        })
+    }
+}
@@ -0,0 +1,602 @@
+//! Protocol level types that only depend on `core` and `alloc`, usable
+//! without linking the VISA runtime.
+
+use crate::error::*;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use bitflags::bitflags;
+
+/// IEEE 488.2 arbitrary block header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockHeader {
+    /// `#<digits><length>`, the payload is `length` bytes long.
+    Definite { digits: usize, length: usize },
+    /// `#0`, the payload runs until the final newline sent with END.
+    Indefinite,
+}
+
+impl BlockHeader {
+    /// Number of bytes taken by the header itself.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Definite { digits, .. } => 2 + digits,
+            Self::Indefinite => 2,
+        }
+    }
+}
+
+impl TryFrom<&[u8]> for BlockHeader {
+    type Error = Error;
+
+    fn try_from(value: &[u8]) -> core::result::Result<Self, Self::Error> {
+        let invalid = || {
+            let header = &value[..value.len().min(11)];
+            Error::InvalidBlockHeader(String::from_utf8_lossy(header).to_string())
+        };
+
+        let [b'#', digits, rest @ ..] = value else {
+            return Err(invalid());
+        };
+        let digits = match *digits {
+            b'0' => return Ok(Self::Indefinite),
+            digits @ b'1'..=b'9' => (digits - b'0') as usize,
+            _ => return Err(invalid()),
+        };
+        let length = rest
+            .get(..digits)
+            .and_then(|length| core::str::from_utf8(length).ok())
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(invalid)?;
+
+        Ok(Self::Definite { digits, length })
+    }
+}
+
+/// Extracts the payload of an IEEE 488.2 definite (`#<n>`) or indefinite
+/// (`#0`) length arbitrary block.
+pub fn parse_block(data: &[u8]) -> Result<&[u8]> {
+    let header = BlockHeader::try_from(data)?;
+    let payload = &data[header.size()..];
+    match header {
+        BlockHeader::Definite { length, .. } => {
+            payload.get(..length).ok_or(Error::IncompleteBlock {
+                length: payload.len(),
+                expected: length,
+            })
+        }
+        BlockHeader::Indefinite => Ok(payload.strip_suffix(b"\n").unwrap_or(payload)),
+    }
+}
+
+/// SCPI numeric response, including the special values SCPI encodes as
+/// sentinel numbers.
+///
+/// `MAXimum` and `MINimum` are command parameters, instruments answer queries
+/// with the resolved number, so they are not accepted as responses and fail
+/// with `NumericParse`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScpiNumber {
+    Value(f64),
+    /// `9.9E37` or `INF`
+    Infinity,
+    /// `-9.9E37` or `-INF`
+    NegativeInfinity,
+    /// `9.91E37` or `NAN`
+    NotANumber,
+    /// `N/A`, the instrument has no value to report.
+    NotAvailable,
+}
+
+impl TryFrom<&str> for ScpiNumber {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "N/A" | "NA" => return Ok(Self::NotAvailable),
+            "INF" | "+INF" => return Ok(Self::Infinity),
+            "-INF" => return Ok(Self::NegativeInfinity),
+            "NAN" => return Ok(Self::NotANumber),
+            _ => {}
+        }
+        let number: f64 = value
+            .trim()
+            .parse()
+            .map_err(|_| Error::NumericParse(value.to_string()))?;
+
+        Ok(match number {
+            9.9e37 => Self::Infinity,
+            -9.9e37 => Self::NegativeInfinity,
+            9.91e37 => Self::NotANumber,
+            number => Self::Value(number),
+        })
+    }
+}
+
+/// Parses a numeric response as `f64`, mapping the infinity and NaN
+/// sentinels. Fails with `NumericParse` if the instrument reports no value.
+pub fn parse_f64(value: &str) -> Result<f64> {
+    match ScpiNumber::try_from(value)? {
+        ScpiNumber::NotAvailable => Err(Error::NumericParse(value.to_string())),
+        number => Ok(number.into()),
+    }
+}
+
+impl From<ScpiNumber> for f64 {
+    fn from(value: ScpiNumber) -> Self {
+        match value {
+            ScpiNumber::Value(value) => value,
+            ScpiNumber::Infinity => f64::INFINITY,
+            ScpiNumber::NegativeInfinity => f64::NEG_INFINITY,
+            ScpiNumber::NotANumber | ScpiNumber::NotAvailable => f64::NAN,
+        }
+    }
+}
+
+/// Strips the surrounding double quotes of a SCPI string response and
+/// collapses the `""` escape sequences to `"`.
+pub fn unquote(value: &str) -> String {
+    let value = value.trim();
+    match value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        Some(value) => value.replace("\"\"", "\""),
+        None => value.to_string(),
+    }
+}
+
+/// Strips the arbitrary block header some instruments wrap a list response
+/// in, such as `#212 1.0,2.0,3.0`, responses without one are returned as is.
+pub fn strip_list_block(response: &str) -> Result<&str> {
+    let mut list = response.trim();
+    if list.starts_with('#') {
+        let header = BlockHeader::try_from(list.as_bytes())?;
+        list = &list[header.size()..];
+        if let BlockHeader::Definite { length, .. } = header {
+            list = list.get(..length).unwrap_or(list);
+        }
+    }
+    Ok(list)
+}
+
+/// Parses a `SYSTem:VERSion?` response such as `1999.0` as (year, revision).
+pub fn parse_scpi_version(response: &str) -> Result<(u16, u16)> {
+    let (year, revision) = response
+        .trim()
+        .split_once('.')
+        .ok_or_else(|| Error::ScpiVersionParse(response.to_string()))?;
+    let year = year
+        .parse()
+        .map_err(|_| Error::ScpiVersionParse(response.to_string()))?;
+    let revision = revision
+        .parse()
+        .map_err(|_| Error::ScpiVersionParse(response.to_string()))?;
+    Ok((year, revision))
+}
+
+/// Splits a comma separated list response into its unquoted items.
+///
+/// Commas within quoted strings do not split, so `"a,b","c"` yields `a,b` and
+/// `c`. An empty response has no items.
+pub fn split_list(response: &str) -> Vec<String> {
+    let response = response.trim();
+    if response.is_empty() {
+        return Vec::new();
+    }
+
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (index, character) in response.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                items.push(unquote(&response[start..index]));
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(unquote(&response[start..]));
+    items
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identification {
+    pub manufacturer: String,
+    pub model: String,
+    pub serial: String,
+    pub firmware: String,
+}
+
+impl TryFrom<&str> for Identification {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let parts: Vec<&str> = value.trim().split(',').collect();
+
+        if parts.len() != 4 {
+            return Err(Error::IdentityParse(value.to_string()));
+        }
+
+        Ok(Self {
+            manufacturer: parts[0].to_string(),
+            model: parts[1].to_string(),
+            serial: parts[2].to_string(),
+            firmware: parts[3].to_string(),
+        })
+    }
+}
+
+/// Entry of the SCPI error queue, `<code>,"<message>"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScpiError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl TryFrom<&str> for ScpiError {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let (code, message) = value
+            .trim()
+            .split_once(',')
+            .ok_or_else(|| Error::ScpiErrorParse(value.to_string()))?;
+        let code = code
+            .trim()
+            .parse()
+            .map_err(|_| Error::ScpiErrorParse(value.to_string()))?;
+
+        Ok(Self {
+            code,
+            message: unquote(message),
+        })
+    }
+}
+
+/// Routine instrument health check, see `Session::health_snapshot`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthSnapshot {
+    pub status_byte: StatusByteRegister,
+    pub event_status: StandardEventStatusRegister,
+    pub errors: Vec<ScpiError>,
+}
+
+bitflags! {
+    /// Standard Event Status Register (SESR)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StandardEventStatusRegister : u8 {
+        /// Set when all pending operations have completed after `*OPC`.
+        const OPERATION_COMPLETE = 1 << 0;
+        /// Indicates that the device requests to become controller-in-charge.
+        const REQUEST_CONTROL = 1 << 1;
+        /// A query was improperly formed or the response queue overflowed.
+        const QUERY_ERROR = 1 << 2;
+        /// Instrument-dependent error condition.
+        const DEVICE_SPECIFIC_ERROR = 1 << 3;
+        /// Command could not be executed due to current instrument state.
+        const EXECUTION_ERROR = 1 << 4;
+        /// Syntax or semantic error in a received command.
+        const COMMAND_ERROR = 1 << 5;
+        /// Local control or front-panel action occurred.
+        const USER_REQUEST = 1 << 6;
+        /// Device power-on event detected.
+        const POWER_ON = 1 << 7;
+        // The source may set any bits.
+        const _ = !0;
+    }
+}
+
+impl TryFrom<&str> for StandardEventStatusRegister {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| Error::StandardEventStatusRegisterParse(value.to_string()))?;
+
+        Ok(Self::from_bits_retain(value))
+    }
+}
+
+bitflags! {
+    /// Standard Event Status Enable Register (SESER)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StandardEventStatusEnableRegister : u8 {
+        /// Set when all pending operations have completed after `*OPC`.
+        const OPERATION_COMPLETE = 1 << 0;
+        /// Indicates that the device requests to become controller-in-charge.
+        const REQUEST_CONTROL = 1 << 1;
+        /// A query was improperly formed or the response queue overflowed.
+        const QUERY_ERROR = 1 << 2;
+        /// Instrument-dependent error condition.
+        const DEVICE_SPECIFIC_ERROR = 1 << 3;
+        /// Command could not be executed due to current instrument state.
+        const EXECUTION_ERROR = 1 << 4;
+        /// Syntax or semantic error in a received command.
+        const COMMAND_ERROR = 1 << 5;
+        /// Local control or front-panel action occurred.
+        const USER_REQUEST = 1 << 6;
+        /// Device power-on event detected.
+        const POWER_ON = 1 << 7;
+        // The source may set any bits.
+        const _ = !0;
+    }
+}
+
+impl TryFrom<&str> for StandardEventStatusEnableRegister {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| Error::StandardEventStatusRegisterParse(value.to_string()))?;
+
+        Ok(Self::from_bits_retain(value))
+    }
+}
+
+impl StandardEventStatusEnableRegister {
+    pub fn value(&self) -> u8 {
+        self.bits()
+    }
+}
+
+bitflags! {
+    /// Status Byte Register (STB)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StatusByteRegister: u8 {
+        /// Bit 3 (0x08): Questionable Status Summary (QUES).
+        ///
+        /// Set when one or more conditions in the Questionable Status
+        /// condition register group are active.
+        const QUESTIONABLE_STATUS_SUMMARY = 1 << 3;
+        /// Bit 4 (0x10): Message Available (MAV).
+        ///
+        /// Set when the instrument has one or more response messages
+        /// available in the output buffer.
+        const MESSAGE_AVAILABLE = 1 << 4;
+        /// Bit 5 (0x20): Event Status Bit (ESB).
+        ///
+        /// Set when at least one enabled event exists:
+        /// (ESR & ESE) != 0.
+        const EVENT_STATUS_BIT = 1 << 5;
+        /// Bit 6 (0x40): Request Service / Master Summary Status (RQS/MSS).
+        ///
+        /// Indicates that the instrument is requesting service (SRQ).
+        /// This bit reflects the SRQ state and is not directly writable.
+        const REQUEST_SERVICE = 1 << 6;
+        /// Bit 7 (0x80): Operation Status Summary (OPER).
+        ///
+        /// Set when one or more conditions in the Operation Status
+        /// condition register group are active.
+        const OPERATION_STATUS_SUMMARY = 1 << 7;
+        // The source may set any bits.
+        const _ = !0;
+    }
+}
+
+impl TryFrom<&str> for StatusByteRegister {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| Error::StatusByteRegisterQueryParse(value.to_string()))?;
+
+        Ok(Self::from_bits_retain(value))
+    }
+}
+
+bitflags! {
+    /// Service Request Enable Register (SRE)
+    ///
+    /// Determines which bits in the Status Byte Register will
+    /// generate a service request (SRQ) when set.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ServiceRequestEnable: u8 {
+        /// Bit 3: Enable service request on Questionable Status
+        const QUESTIONABLE_STATUS = 1 << 3;
+        /// Bit 4: Enable service request on Message Available
+        const MESSAGE_AVAILABLE = 1 << 4;
+        /// Bit 5: Enable service request on Event Status
+        const EVENT_STATUS = 1 << 5;
+        // Bit 6 (RQS/MSS) is read-only and cannot be enabled
+        /// Bit 7: Enable service request on Operation Status
+        const OPERATION_STATUS = 1 << 7;
+
+        const _ = !0;
+    }
+}
+
+impl TryFrom<&str> for ServiceRequestEnable {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| Error::ServiceRequestEnableQueryParse(value.to_string()))?;
+
+        Ok(Self::from_bits_retain(value))
+    }
+}
+
+impl ServiceRequestEnable {
+    pub fn value(&self) -> u8 {
+        self.bits()
+    }
+
+    /// Builds the enable mask for the QUES, MAV, ESB and OPER bits set in
+    /// `status`, other bits are ignored.
+    pub fn from_status_bits(status: StatusByteRegister) -> Self {
+        let mut enable = Self::empty();
+        enable.set(
+            Self::QUESTIONABLE_STATUS,
+            status.contains(StatusByteRegister::QUESTIONABLE_STATUS_SUMMARY),
+        );
+        enable.set(
+            Self::MESSAGE_AVAILABLE,
+            status.contains(StatusByteRegister::MESSAGE_AVAILABLE),
+        );
+        enable.set(
+            Self::EVENT_STATUS,
+            status.contains(StatusByteRegister::EVENT_STATUS_BIT),
+        );
+        enable.set(
+            Self::OPERATION_STATUS,
+            status.contains(StatusByteRegister::OPERATION_STATUS_SUMMARY),
+        );
+        enable
+    }
+
+    /// Status byte bits that raise a service request with this mask.
+    pub fn status_bits(&self) -> StatusByteRegister {
+        let mut status = StatusByteRegister::empty();
+        status.set(
+            StatusByteRegister::QUESTIONABLE_STATUS_SUMMARY,
+            self.contains(Self::QUESTIONABLE_STATUS),
+        );
+        status.set(
+            StatusByteRegister::MESSAGE_AVAILABLE,
+            self.contains(Self::MESSAGE_AVAILABLE),
+        );
+        status.set(
+            StatusByteRegister::EVENT_STATUS_BIT,
+            self.contains(Self::EVENT_STATUS),
+        );
+        status.set(
+            StatusByteRegister::OPERATION_STATUS_SUMMARY,
+            self.contains(Self::OPERATION_STATUS),
+        );
+        status
+    }
+}
+
+bitflags! {
+    /// Parallel Poll Enable Register (PRE)
+    ///
+    /// Determines which bits in the Status Byte Register contribute to the
+    /// individual status (ist) message.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct ParallelPollEnableRegister: u16 {
+        /// Bit 3: Questionable Status
+        const QUESTIONABLE_STATUS = 1 << 3;
+        /// Bit 4: Message Available
+        const MESSAGE_AVAILABLE = 1 << 4;
+        /// Bit 5: Event Status
+        const EVENT_STATUS = 1 << 5;
+        /// Bit 6: Master Summary Status
+        const MASTER_SUMMARY_STATUS = 1 << 6;
+        /// Bit 7: Operation Status
+        const OPERATION_STATUS = 1 << 7;
+        // Bits 8 to 15 are device specific.
+        const _ = !0;
+    }
+}
+
+impl TryFrom<&str> for ParallelPollEnableRegister {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| Error::ParallelPollEnableRegisterParse(value.to_string()))?;
+
+        Ok(Self::from_bits_retain(value))
+    }
+}
+
+impl ParallelPollEnableRegister {
+    pub fn value(&self) -> u16 {
+        self.bits()
+    }
+}
+
+pub trait AsScpi {
+    fn as_scpi(&self) -> String;
+}
+
+macro_rules! impl_as_scpi_integer {
+    ($($type:ty),*) => {
+        $(
+            impl AsScpi for $type {
+                fn as_scpi(&self) -> String {
+                    self.to_string()
+                }
+            }
+        )*
+    };
+}
+
+impl_as_scpi_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+macro_rules! impl_as_scpi_float {
+    ($($type:ty),*) => {
+        $(
+            /// Scientific notation, infinities and NaN use the SCPI sentinels.
+            impl AsScpi for $type {
+                fn as_scpi(&self) -> String {
+                    match *self {
+                        value if value.is_nan() => "9.91E37".to_string(),
+                        <$type>::INFINITY => "9.9E37".to_string(),
+                        <$type>::NEG_INFINITY => "-9.9E37".to_string(),
+                        value => format!("{:E}", value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_as_scpi_float!(f32, f64);
+
+impl AsScpi for bool {
+    fn as_scpi(&self) -> String {
+        match self {
+            true => "ON".to_string(),
+            false => "OFF".to_string(),
+        }
+    }
+}
+
+/// Quoted string, embedded quotes are doubled.
+impl AsScpi for &str {
+    fn as_scpi(&self) -> String {
+        format!("\"{}\"", self.replace('"', "\"\""))
+    }
+}
+
+impl AsScpi for String {
+    fn as_scpi(&self) -> String {
+        self.as_str().as_scpi()
+    }
+}
+
+/// Replaces each `{}` in `template` with the next argument in SCPI format.
+pub fn fill_template(template: &str, args: &[&dyn AsScpi]) -> Result<String> {
+    let placeholders = template.matches("{}").count();
+    if placeholders != args.len() {
+        return Err(Error::TemplateArguments {
+            placeholders,
+            arguments: args.len(),
+        });
+    }
+
+    let mut command = String::with_capacity(template.len());
+    let mut parts = template.split("{}");
+    command.push_str(parts.next().unwrap_or_default());
+    for (part, arg) in parts.zip(args) {
+        command.push_str(&arg.as_scpi());
+        command.push_str(part);
+    }
+    Ok(command)
+}
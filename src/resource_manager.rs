@@ -1,12 +1,16 @@
 use crate::{
     error::*,
     session::Session,
-    utility::{AccessMode, MandatoryCommands, Timeout, stringify_buffer},
+    socket_session::SocketSession,
+    utility::{self, AccessMode, MandatoryCommands, Timeout, stringify_buffer},
 };
-use std::ffi::CString;
+use std::{ffi::CString, marker::PhantomData, time::Duration};
 use tracing::{debug, error};
 use visa_bindings::*;
 
+/// Time each resource gets to answer `*IDN?` during identification lookup.
+const IDENTIFICATION_TIMEOUT: Duration = Duration::from_secs(1);
+
 #[derive(Debug)]
 pub struct ResourceManager {
     value: ViSession,
@@ -36,6 +40,17 @@ impl ResourceManager {
         Ok(Self { value: session })
     }
 
+    /// Describes a VISA status code that is not tied to a session, the raw
+    /// code is appended in hexadecimal.
+    pub fn status_description(&self, status: ViStatus) -> Result<String> {
+        utility::status_description(self.value, status)
+    }
+
+    /// Returns the first resource matching `expression` whose `*IDN?`
+    /// response has the given manufacturer, model and serial number.
+    ///
+    /// An empty string matches any value, each resource gets a short timeout
+    /// to answer.
     pub fn find_resource_by_identification<T: AsRef<str>>(
         &self,
         expression: T,
@@ -55,6 +70,11 @@ impl ResourceManager {
                     }
                 };
 
+            if let Err(error) = session.set_timeout(Timeout::Custom(IDENTIFICATION_TIMEOUT)) {
+                error!("Failed to set identification timeout: {}", error);
+                continue;
+            }
+
             let identification = match session.identification_query() {
                 Ok(identification) => identification,
                 Err(error) => {
@@ -63,9 +83,12 @@ impl ResourceManager {
                 }
             };
 
-            if identification.manufacturer == manufacturer.as_ref()
-                && identification.model == model.as_ref()
-                && identification.serial == serial.as_ref()
+            let matches = |expected: &T, actual: &str| {
+                expected.as_ref().is_empty() || expected.as_ref() == actual
+            };
+            if matches(&manufacturer, &identification.manufacturer)
+                && matches(&model, &identification.model)
+                && matches(&serial, &identification.serial)
             {
                 return Ok(resource);
             }
@@ -89,17 +112,62 @@ impl ResourceManager {
                 access_mode.into(),
                 timeout.try_into()?,
                 &mut session,
-            ))?
-        };
+            ))
+        }
+        .map_err(|error| {
+            error!("Failed to open resource {:?}: {}", resource, error);
+            Error::OpenFailed {
+                resource: resource.to_string_lossy().to_string(),
+                source: Box::new(error),
+            }
+        })?;
         debug!(
             "Resource {:?} opened with completion code: {}",
             resource, completion_code
         );
 
-        Ok(Session::new(session))
+        Ok(Session::new(session, access_mode))
+    }
+
+    /// Starts configuring a session for `resource`, opened with
+    /// [`SessionBuilder::open`].
+    pub fn session_builder<T: AsRef<str>>(&self, resource: T) -> SessionBuilder<'_> {
+        SessionBuilder {
+            resource_manager: self,
+            resource: resource.as_ref().to_string(),
+            access_mode: AccessMode::None,
+            open_timeout: Timeout::Immediate,
+            io_timeout: None,
+            termination_char: None,
+            read_buffer_size: None,
+        }
+    }
+
+    /// Opens a raw socket resource (`TCPIP<n>::<host>::<port>::SOCKET`) with
+    /// the defaults raw socket instruments expect.
+    ///
+    /// The passport suffix of `resource` is passed to VISA unchanged by
+    /// [`ResourceManager::open_session`], this only rejects non socket names.
+    pub fn open_socket_session<T: AsRef<str>>(
+        &self,
+        resource: T,
+        access_mode: AccessMode,
+        timeout: Timeout,
+    ) -> Result<SocketSession> {
+        if !resource
+            .as_ref()
+            .trim()
+            .to_ascii_uppercase()
+            .ends_with("::SOCKET")
+        {
+            return Err(Error::InvalidResourceName);
+        }
+        let session = self.open_session(resource, access_mode, timeout)?;
+        SocketSession::new(session)
     }
 
-    /// Finds all instruments that match the expression.
+    /// Finds all instruments that match the expression, no match yields an
+    /// empty list rather than an error.
     ///
     /// | Special Characters and Operators | Meaning                                                                                                                                                                                                                                                |
     /// | -------------------------------- | ------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------ |
@@ -112,44 +180,201 @@ impl ResourceManager {
     /// | Exp\|exp                         | Matches either the preceding or following expression. The or operator \| matches the entire expression that precedes or follows it and not just the character that precedes or follows it. For example, VXI\|GPIB means (VXI)\|(GPIB), not VX(I\|G)PIB.|
     /// | (exp)                            | Grouping characters or expressions.                                                                                                                                                                                                                    |
     pub fn find_resources<T: AsRef<str>>(&self, expression: T) -> Result<Vec<String>> {
+        self.find_resources_iter(expression)?.collect()
+    }
+
+    /// Finds all instruments that match the expression, invoking `callback` for
+    /// each resource as soon as VISA yields it.
+    ///
+    /// See [`ResourceManager::find_resources`] for the expression syntax.
+    pub fn find_resources_with_callback<T: AsRef<str>>(
+        &self,
+        expression: T,
+        mut callback: impl FnMut(&str),
+    ) -> Result<()> {
+        for resource in self.find_resources_iter(expression)? {
+            callback(&resource?);
+        }
+        Ok(())
+    }
+
+    /// Finds the instruments that match the expression lazily, each
+    /// `viFindNext` call is made when the iterator is advanced.
+    ///
+    /// See [`ResourceManager::find_resources`] for the expression syntax.
+    pub fn find_resources_iter<T: AsRef<str>>(
+        &self,
+        expression: T,
+    ) -> Result<ResourceIterator<'_>> {
         let mut list: ViFindList = 0;
         let mut count: ViUInt32 = 0;
         let mut description = [0u8; VI_FIND_BUFLEN as _];
 
         let expression = CString::new(expression.as_ref()).map_err(|_| Error::InvalidNullString)?;
 
-        let completion_code = unsafe {
+        let result = unsafe {
             CompletionCode::try_from(viFindRsrc(
                 self.value,
                 expression.as_ptr() as _,
                 &mut list,
                 &mut count,
                 description.as_mut_ptr() as _,
-            ))?
+            ))
+        };
+        // Some drivers, NI-VISA included, report no match as an error.
+        let completion_code = match result {
+            Ok(completion_code) => completion_code,
+            Err(Error::ResourceNotFound) => {
+                debug!("No resources match {:?}", expression);
+                return Ok(ResourceIterator {
+                    list: VI_NULL as ViFindList,
+                    first: None,
+                    remaining: 0,
+                    resource_manager: PhantomData,
+                });
+            }
+            Err(error) => return Err(error),
         };
         debug!("Found resources with completion code: {}", completion_code);
 
-        let mut resources = vec![];
+        let first = match count {
+            0 => None,
+            _ => Some(stringify_buffer(&description)),
+        };
+        Ok(ResourceIterator {
+            list,
+            first,
+            remaining: (count as usize).saturating_sub(1),
+            resource_manager: PhantomData,
+        })
+    }
+}
+
+/// Session configuration applied right after opening, see
+/// [`ResourceManager::session_builder`].
+#[derive(Debug, Clone)]
+pub struct SessionBuilder<'a> {
+    resource_manager: &'a ResourceManager,
+    resource: String,
+    access_mode: AccessMode,
+    open_timeout: Timeout,
+    io_timeout: Option<Timeout>,
+    termination_char: Option<u8>,
+    read_buffer_size: Option<u32>,
+}
+
+impl SessionBuilder<'_> {
+    /// Lock acquired when opening, `AccessMode::None` by default.
+    pub fn access_mode(mut self, access_mode: AccessMode) -> Self {
+        self.access_mode = access_mode;
+        self
+    }
+
+    /// Time `viOpen` waits for a lock, `Timeout::Immediate` by default.
+    pub fn open_timeout(mut self, timeout: Timeout) -> Self {
+        self.open_timeout = timeout;
+        self
+    }
+
+    /// I/O timeout (`VI_ATTR_TMO_VALUE`) of the opened session.
+    pub fn io_timeout(mut self, timeout: Timeout) -> Self {
+        self.io_timeout = Some(timeout);
+        self
+    }
+
+    /// Terminates reads on `char`, also enabling termination.
+    pub fn termination_char(mut self, char: u8) -> Self {
+        self.termination_char = Some(char);
+        self
+    }
 
-        if count < 1 {
-            return Ok(resources);
+    /// Size of the VISA read buffer, see [`Session::set_read_buffer_size`].
+    pub fn read_buffer_size(mut self, bytes: u32) -> Self {
+        self.read_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Opens the session and applies the configuration, the session is closed
+    /// again if that fails.
+    pub fn open(self) -> Result<Session> {
+        let session = self.resource_manager.open_session(
+            &self.resource,
+            self.access_mode,
+            self.open_timeout,
+        )?;
+        if let Some(timeout) = self.io_timeout {
+            session.set_timeout(timeout)?;
+        }
+        if let Some(char) = self.termination_char {
+            session.set_termination_char(char)?;
+            session.set_termination_char_enabled(true)?;
         }
+        if let Some(bytes) = self.read_buffer_size {
+            session.set_read_buffer_size(bytes)?;
+        }
+        Ok(session)
+    }
+}
 
-        let resource = stringify_buffer(&description)?;
-        resources.push(resource);
+/// Resources found by [`ResourceManager::find_resources_iter`], the find list
+/// is closed on drop.
+#[derive(Debug)]
+pub struct ResourceIterator<'a> {
+    list: ViFindList,
+    first: Option<Result<String>>,
+    remaining: usize,
+    resource_manager: PhantomData<&'a ResourceManager>,
+}
 
-        for _ in 1..count {
-            let completion_code = unsafe {
-                CompletionCode::try_from(viFindNext(list, description.as_mut_ptr() as _))?
-            };
-            debug!(
-                "Found next resource with completion code: {}",
+impl Drop for ResourceIterator<'_> {
+    fn drop(&mut self) {
+        if self.list == VI_NULL as ViFindList {
+            return;
+        }
+        let result = unsafe { CompletionCode::try_from(viClose(self.list)) };
+        match result {
+            Ok(completion_code) => debug!(
+                "Find list was closed with completion code: {}",
                 completion_code
-            );
-            let resource = stringify_buffer(&description)?;
-            resources.push(resource);
+            ),
+            Err(error) => error!("Closing find list failed with code: {}", error),
+        }
+    }
+}
+
+impl Iterator for ResourceIterator<'_> {
+    type Item = Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(first) = self.first.take() {
+            return Some(first);
         }
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+
+        let mut description = [0u8; VI_FIND_BUFLEN as _];
+        let result = unsafe {
+            CompletionCode::try_from(viFindNext(self.list, description.as_mut_ptr() as _))
+        };
+        match result {
+            Ok(completion_code) => {
+                debug!(
+                    "Found next resource with completion code: {}",
+                    completion_code
+                );
+                Some(stringify_buffer(&description))
+            }
+            Err(error) => {
+                self.remaining = 0;
+                Some(Err(error))
+            }
+        }
+    }
 
-        Ok(resources)
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let count = self.remaining + self.first.is_some() as usize;
+        (0, Some(count))
     }
 }
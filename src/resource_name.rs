@@ -0,0 +1,182 @@
+//! Parsing of VISA resource names, usable without linking the VISA runtime.
+
+use crate::error::*;
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceType {
+    Usb,
+    Tcpip,
+    Gpib,
+    Asrl,
+    Vxi,
+    Pxi,
+}
+
+/// Interface specific part of a resource name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceAddress {
+    /// Resource classes without an address, such as `ASRL3::INSTR` or
+    /// `GPIB0::INTFC`.
+    None,
+    Usb {
+        manufacturer_id: u16,
+        model_code: u16,
+        serial_number: String,
+        interface_number: Option<u16>,
+    },
+    Tcpip {
+        host: String,
+        /// LAN device name, `inst0` or `hislip0` for example.
+        device_name: Option<String>,
+    },
+    Socket {
+        host: String,
+        port: u16,
+    },
+    Gpib {
+        primary_address: u8,
+        secondary_address: Option<u8>,
+    },
+    Vxi {
+        logical_address: u16,
+    },
+    /// PXI addresses come in several forms and are kept as written.
+    Pxi {
+        address: String,
+    },
+}
+
+/// Structured form of a VISA resource name such as
+/// `TCPIP0::192.168.0.5::inst0::INSTR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceName {
+    pub interface_type: InterfaceType,
+    pub board: u16,
+    pub address: ResourceAddress,
+    /// `INSTR`, `SOCKET`, `INTFC`, ... (`INSTR` when omitted).
+    pub resource_class: String,
+}
+
+const RESOURCE_CLASSES: [&str; 7] = [
+    "INSTR",
+    "SOCKET",
+    "INTFC",
+    "BACKPLANE",
+    "MEMACC",
+    "SERVANT",
+    "RAW",
+];
+
+impl TryFrom<&str> for ResourceName {
+    type Error = Error;
+
+    fn try_from(value: &str) -> core::result::Result<Self, Self::Error> {
+        let mut parts: Vec<&str> = value.trim().split("::").collect();
+
+        let resource_class = match parts.last() {
+            Some(last) if RESOURCE_CLASSES.contains(&last.to_ascii_uppercase().as_str()) => {
+                parts.pop().unwrap_or_default().to_ascii_uppercase()
+            }
+            _ => "INSTR".to_string(),
+        };
+
+        let (interface, address) = parts.split_first().ok_or(Error::InvalidResourceName)?;
+        let interface = interface.to_ascii_uppercase();
+        let prefix_length = interface
+            .find(|char: char| char.is_ascii_digit())
+            .unwrap_or(interface.len());
+        let (prefix, board) = interface.split_at(prefix_length);
+        let board = match board {
+            "" => 0,
+            board => board.parse().map_err(|_| Error::InvalidResourceName)?,
+        };
+
+        let interface_type = match prefix {
+            "USB" => InterfaceType::Usb,
+            "TCPIP" => InterfaceType::Tcpip,
+            "GPIB" => InterfaceType::Gpib,
+            "ASRL" => InterfaceType::Asrl,
+            "VXI" => InterfaceType::Vxi,
+            "PXI" => InterfaceType::Pxi,
+            _ => return Err(Error::InvalidResourceName),
+        };
+
+        let address = match (interface_type, resource_class.as_str(), address) {
+            (_, _, []) => ResourceAddress::None,
+            (InterfaceType::Usb, _, [manufacturer_id, model_code, serial_number, rest @ ..])
+                if rest.len() <= 1 =>
+            {
+                ResourceAddress::Usb {
+                    manufacturer_id: parse_number(manufacturer_id)?,
+                    model_code: parse_number(model_code)?,
+                    serial_number: serial_number.to_string(),
+                    interface_number: rest
+                        .first()
+                        .map(|number| parse_number(number))
+                        .transpose()?,
+                }
+            }
+            (InterfaceType::Tcpip, "SOCKET", [host, port]) => ResourceAddress::Socket {
+                host: host.to_string(),
+                port: port.parse().map_err(|_| Error::InvalidResourceName)?,
+            },
+            (InterfaceType::Tcpip, _, [host, rest @ ..]) if rest.len() <= 1 => {
+                ResourceAddress::Tcpip {
+                    host: host.to_string(),
+                    device_name: rest.first().map(|name| name.to_string()),
+                }
+            }
+            (InterfaceType::Gpib, _, [primary_address, rest @ ..]) if rest.len() <= 1 => {
+                ResourceAddress::Gpib {
+                    primary_address: parse_number(primary_address)?,
+                    secondary_address: rest
+                        .first()
+                        .map(|address| parse_number(address))
+                        .transpose()?,
+                }
+            }
+            (InterfaceType::Vxi, _, [logical_address]) => ResourceAddress::Vxi {
+                logical_address: parse_number(logical_address)?,
+            },
+            (InterfaceType::Pxi, _, address) => ResourceAddress::Pxi {
+                address: address.join("::"),
+            },
+            _ => return Err(Error::InvalidResourceName),
+        };
+
+        Ok(Self {
+            interface_type,
+            board,
+            address,
+            resource_class,
+        })
+    }
+}
+
+impl FromStr for ResourceName {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        Self::try_from(value)
+    }
+}
+
+/// Parses a decimal or `0x` prefixed hexadecimal number.
+fn parse_number<T: TryFrom<u32>>(value: &str) -> Result<T> {
+    let value = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    value
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or(Error::InvalidResourceName)
+}
@@ -1,102 +1,1215 @@
 use crate::{
+    async_io::AsyncState,
     error::*,
-    utility::{FlushMode, MandatoryCommands, Timeout},
+    utility::{
+        self, AccessMode, AsScpi, BlockHeader, FdcMode, FlushMode, HealthSnapshot, IoProtocol,
+        MandatoryCommands, ReadOptions, ScpiError, ScpiNumber, SerialConfig, SerialEnd,
+        SerialErrorFlags, StandardEventStatusRegister, Timeout, VxiCommandMode, fill_template,
+        parse_block, parse_f64, split_list, stringify_buffer, strip_list_block, unquote,
+    },
 };
-use tracing::{debug, error};
+use std::{
+    borrow::Cow,
+    ffi::CString,
+    io::Read,
+    ops::Deref,
+    str::FromStr,
+    sync::{
+        Mutex, PoisonError, RwLock,
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, warn};
 use visa_bindings::*;
 
+/// Number of bytes requested from each `viRead` call by default.
+const DEFAULT_CHUNK_SIZE: usize = 4096;
+
+/// Upper bound on `SYSTem:ERRor?` reads when draining the error queue.
+const ERROR_QUEUE_LIMIT: usize = 128;
+
+/// Upper bound on the pages fetched by `query_paged`.
+const PAGE_LIMIT: usize = 1024;
+
+type CloseFailureHook = Box<dyn Fn(&Error) + Send + Sync>;
+
+static CLOSE_FAILURE_HOOK: RwLock<Option<CloseFailureHook>> = RwLock::new(None);
+
+/// Sets a process wide hook called whenever closing a dropped [`Session`]
+/// fails, in addition to the error log.
+pub fn set_close_failure_hook(hook: impl Fn(&Error) + Send + Sync + 'static) {
+    *CLOSE_FAILURE_HOOK
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(Box::new(hook));
+}
+
+/// Builds the bytes written for `command`, with the checksum of the command
+/// appended when one is set.
+pub(crate) fn checksum_payload(command: &[u8], checksum: Option<fn(&[u8]) -> u8>) -> Cow<'_, [u8]> {
+    match checksum {
+        Some(checksum) => {
+            let mut payload = command.to_vec();
+            payload.push(checksum(command));
+            Cow::Owned(payload)
+        }
+        None => Cow::Borrowed(command),
+    }
+}
+
+/// Narrows an attribute read into a `ViAttrState`.
+///
+/// VISA writes the attribute's native width at the start of the buffer, on
+/// the little endian targets VISA supports the zeroed upper bytes leave the
+/// value intact.
+pub(crate) fn narrow_attribute<T: TryFrom<ViAttrState>>(value: ViAttrState) -> Result<T> {
+    T::try_from(value).map_err(|_| Error::AttributeOverflow(value))
+}
+
+/// Commands allowed while an overlapped operation is pending, they only
+/// observe its completion. Matched as prefixes, `*OPC` also covers `*OPC?`.
+const COMPLETION_COMMANDS: [&[u8]; 4] = [b"*OPC", b"*WAI", b"*ESR?", b"*STB?"];
+
+/// Signed attributes of type `ViInt16`, see [`Session::get_attribute_i64`].
+const SIGNED_16_BIT_ATTRIBUTES: [ViAttr; 7] = [
+    VI_ATTR_SLOT,
+    VI_ATTR_VXI_LA,
+    VI_ATTR_CMDR_LA,
+    VI_ATTR_MAINFRAME_LA,
+    VI_ATTR_TRIG_ID,
+    VI_ATTR_USB_INTFC_NUM,
+    VI_ATTR_USB_PROTOCOL,
+];
+
+/// Signed attributes of type `ViInt32`, see [`Session::get_attribute_i64`].
+const SIGNED_32_BIT_ATTRIBUTES: [ViAttr; 2] = [VI_ATTR_STATUS, VI_ATTR_PXI_RECV_INTR_DATA];
+
+/// Numeric attributes reported by [`Session::attribute_snapshot`].
+const SNAPSHOT_ATTRIBUTES: [(&str, ViAttr); 6] = [
+    ("VI_ATTR_INTF_TYPE", VI_ATTR_INTF_TYPE),
+    ("VI_ATTR_INTF_NUM", VI_ATTR_INTF_NUM),
+    ("VI_ATTR_TMO_VALUE", VI_ATTR_TMO_VALUE),
+    ("VI_ATTR_TERMCHAR", VI_ATTR_TERMCHAR),
+    ("VI_ATTR_TERMCHAR_EN", VI_ATTR_TERMCHAR_EN),
+    ("VI_ATTR_IO_PROT", VI_ATTR_IO_PROT),
+];
+
+/// String attributes reported by [`Session::attribute_snapshot`].
+const SNAPSHOT_STRING_ATTRIBUTES: [(&str, ViAttr); 4] = [
+    ("VI_ATTR_RSRC_NAME", VI_ATTR_RSRC_NAME),
+    ("VI_ATTR_INTF_INST_NAME", VI_ATTR_INTF_INST_NAME),
+    ("VI_ATTR_MANF_NAME", VI_ATTR_MANF_NAME),
+    ("VI_ATTR_MODEL_NAME", VI_ATTR_MODEL_NAME),
+];
+
+/// Open VISA session, closed on drop.
+///
+/// `Session` is `Send` and `Sync`: the handle is a plain integer VISA accepts
+/// from any thread, and all state changed through `&self` is kept in atomics
+/// or mutexes. Moving a session to a worker thread is therefore fine. Calls
+/// sharing it between threads are each thread safe but may interleave, use
+/// [`Session::critical_section`] or [`Session::lock`] to keep a
+/// write/read pair together.
 #[derive(Debug)]
 pub struct Session {
-    value: ViSession,
+    pub(crate) value: ViSession,
+    access_mode: AccessMode,
+    lock_count: AtomicUsize,
+    last_read_count: AtomicUsize,
+    serial_errors: AtomicU8,
+    pub(crate) operation_pending: AtomicBool,
+    strip_xon_xoff: bool,
+    read_chunk_size: usize,
+    max_read_iterations: Option<usize>,
+    clear_mav_after_read: bool,
+    strict_write_length: bool,
+    checksum: Option<fn(&[u8]) -> u8>,
+    min_command_interval: Duration,
+    post_read_delay: Duration,
+    last_write: Mutex<Option<Instant>>,
+    last_completion_code: Mutex<Option<CompletionCode>>,
+    critical_section: Mutex<()>,
+    pub(crate) async_state: Mutex<AsyncState>,
+    end_reached: bool,
+    closed: bool,
 }
 
+// Keeps the documented thread safety from silently regressing when fields
+// are added.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Session>();
+};
+
 impl Drop for Session {
     fn drop(&mut self) {
-        let result = unsafe { CompletionCode::try_from(viClose(self.value)) };
-        match result {
+        if self.closed {
+            return;
+        }
+        match self.release() {
             Ok(completion_code) => debug!(
                 "Session was closed with completion code: {}",
                 completion_code
             ),
-            Err(error) => error!("Closing session failed with code: {}", error),
+            Err(error) => {
+                error!("Closing session failed with code: {}", error);
+                let hook = CLOSE_FAILURE_HOOK
+                    .read()
+                    .unwrap_or_else(PoisonError::into_inner);
+                if let Some(hook) = hook.as_ref() {
+                    hook(&error);
+                }
+            }
         }
     }
 }
 
 impl Session {
-    pub(crate) fn new(session: ViSession) -> Self {
-        Self { value: session }
+    pub(crate) fn new(session: ViSession, access_mode: AccessMode) -> Self {
+        let lock_count = match access_mode {
+            AccessMode::Exclusive | AccessMode::Shared => 1,
+            AccessMode::None => 0,
+        };
+        Self {
+            value: session,
+            access_mode,
+            lock_count: AtomicUsize::new(lock_count),
+            last_read_count: AtomicUsize::new(0),
+            serial_errors: AtomicU8::new(0),
+            operation_pending: AtomicBool::new(false),
+            strip_xon_xoff: false,
+            read_chunk_size: DEFAULT_CHUNK_SIZE,
+            max_read_iterations: None,
+            clear_mav_after_read: false,
+            strict_write_length: true,
+            checksum: None,
+            min_command_interval: Duration::ZERO,
+            post_read_delay: Duration::ZERO,
+            last_write: Mutex::new(None),
+            last_completion_code: Mutex::new(None),
+            critical_section: Mutex::new(()),
+            async_state: Mutex::default(),
+            end_reached: false,
+            closed: false,
+        }
+    }
+
+    /// Closes the session, releasing any locks it still holds first.
+    pub fn close(mut self) -> Result<()> {
+        let completion_code = self.release()?;
+        debug!(
+            "Session was closed with completion code: {}",
+            completion_code
+        );
+        Ok(())
+    }
+
+    fn release(&mut self) -> Result<CompletionCode> {
+        let unlocked = self.unlock_all();
+        if let Err(error) = &unlocked {
+            error!("Unlocking session before closing failed: {}", error);
+        }
+        let completion_code = unsafe { CompletionCode::try_from(viClose(self.value)) };
+        self.closed = true;
+        unlocked.map_err(|error| Error::UnlockOnClose(Box::new(error)))?;
+        completion_code
+    }
+
+    fn unlock_all(&self) -> Result<()> {
+        while self.lock_count.load(Ordering::Acquire) > 0 {
+            self.unlock()?;
+        }
+        Ok(())
+    }
+
+    /// I/O timeout of the session (`VI_ATTR_TMO_VALUE`).
+    pub fn timeout(&self) -> Result<Timeout> {
+        let timeout: ViUInt32 = self.get_attribute(VI_ATTR_TMO_VALUE)?;
+        Ok(timeout.into())
     }
 
     pub fn set_timeout(&self, timeout: Timeout) -> Result<()> {
+        self.set_attribute(VI_ATTR_TMO_VALUE, timeout.try_into()?)
+    }
+
+    /// Termination character configured for reads.
+    pub fn termination_char(&self) -> Result<u8> {
+        self.get_attribute(VI_ATTR_TERMCHAR)
+    }
+
+    /// Returns `true` if reads stop on the termination character.
+    pub fn termination_enabled(&self) -> Result<bool> {
+        let enabled: ViBoolean = self.get_attribute(VI_ATTR_TERMCHAR_EN)?;
+        Ok(enabled != VI_FALSE as ViBoolean)
+    }
+
+    /// Sets the termination character for reads (`VI_ATTR_TERMCHAR`).
+    pub fn set_termination_char(&self, char: u8) -> Result<()> {
+        self.set_attribute(VI_ATTR_TERMCHAR, char as ViAttrState)
+    }
+
+    /// Enables stopping reads on the termination character.
+    ///
+    /// When enabled `viRead` returns as soon as the configured byte is read,
+    /// reporting `CompletionCode::TerminationCharacterRead`. Needed for
+    /// instruments that never assert END, such as most serial devices.
+    pub fn set_termination_char_enabled(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_TERMCHAR_EN, enabled as ViAttrState)
+    }
+
+    /// Sets the XON/XOFF characters used by serial software flow control.
+    ///
+    /// With `VI_ASRL_FLOW_XON_XOFF` flow control the driver pauses writes on XOFF.
+    pub fn set_xon_xoff_chars(&self, xon: u8, xoff: u8) -> Result<()> {
+        self.set_attribute(VI_ATTR_ASRL_XON_CHAR, xon as ViAttrState)?;
+        self.set_attribute(VI_ATTR_ASRL_XOFF_CHAR, xoff as ViAttrState)
+    }
+
+    /// Holds the serial line in the break state for `duration` (1 to 500 ms).
+    pub fn send_break(&self, duration: Duration) -> Result<()> {
+        let length = match duration.as_millis() {
+            length @ 1..=500 => length as ViAttrState,
+            _ => return Err(Error::InvalidBreakLength(duration)),
+        };
+        self.set_attribute(VI_ATTR_ASRL_BREAK_LEN, length)?;
+        self.set_attribute(VI_ATTR_ASRL_BREAK_STATE, VI_STATE_ASSERTED as ViAttrState)?;
+        std::thread::sleep(duration);
+        self.set_attribute(VI_ATTR_ASRL_BREAK_STATE, VI_STATE_UNASSERTED as ViAttrState)
+    }
+
+    /// Configures the line of an `ASRL` resource.
+    pub fn configure_serial(&self, config: &SerialConfig) -> Result<()> {
+        self.set_attribute(VI_ATTR_ASRL_BAUD, config.baud_rate as ViAttrState)?;
+        self.set_attribute(VI_ATTR_ASRL_DATA_BITS, config.data_bits as ViAttrState)?;
+        self.set_attribute(
+            VI_ATTR_ASRL_PARITY,
+            ViUInt16::from(config.parity) as ViAttrState,
+        )?;
+        self.set_attribute(
+            VI_ATTR_ASRL_STOP_BITS,
+            ViUInt16::from(config.stop_bits) as ViAttrState,
+        )?;
+        self.set_attribute(
+            VI_ATTR_ASRL_FLOW_CNTRL,
+            ViUInt16::from(config.flow_control) as ViAttrState,
+        )
+    }
+
+    /// Sets how the end of a message is detected on serial reads.
+    pub fn set_serial_end_in(&self, mode: SerialEnd) -> Result<()> {
+        self.set_attribute(VI_ATTR_ASRL_END_IN, ViUInt16::from(mode) as ViAttrState)
+    }
+
+    /// Sets how the end of a message is marked on serial writes.
+    pub fn set_serial_end_out(&self, mode: SerialEnd) -> Result<()> {
+        self.set_attribute(VI_ATTR_ASRL_END_OUT, ViUInt16::from(mode) as ViAttrState)
+    }
+
+    /// Removes the configured XON/XOFF characters from data returned by `read`.
+    pub fn set_strip_xon_xoff(&mut self, enabled: bool) {
+        self.strip_xon_xoff = enabled;
+    }
+
+    /// Reads the status byte (`viReadSTB`) after every read to clear MAV.
+    ///
+    /// Some older instruments return shifted responses on back to back
+    /// queries unless MAV is cleared this way.
+    pub fn set_clear_mav_after_read(&mut self, enabled: bool) {
+        self.clear_mav_after_read = enabled;
+    }
+
+    /// Access mode the session was opened with.
+    pub fn access_mode(&self) -> AccessMode {
+        self.access_mode
+    }
+
+    /// Returns `true` while this session holds a lock it acquired.
+    pub fn is_locked(&self) -> bool {
+        self.lock_count.load(Ordering::Acquire) > 0
+    }
+
+    /// Current lock state of the resource as reported by VISA.
+    pub fn lock_state(&self) -> Result<AccessMode> {
+        let state: ViAccessMode = self.get_attribute(VI_ATTR_RSRC_LOCK_STATE)?;
+        AccessMode::try_from(state)
+    }
+
+    /// Returns `true` if the session uses the HiSLIP protocol.
+    pub fn is_hislip(&self) -> Result<bool> {
+        let hislip: ViBoolean = self.get_attribute(VI_ATTR_TCPIP_IS_HISLIP)?;
+        Ok(hislip != VI_FALSE as ViBoolean)
+    }
+
+    /// Enables HiSLIP overlapped mode (`VI_ATTR_TCPIP_HISLIP_OVERLAP_EN`).
+    pub fn set_hislip_overlap_enabled(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_TCPIP_HISLIP_OVERLAP_EN, enabled as ViAttrState)
+    }
+
+    /// Sets the maximum HiSLIP message size in kilobytes.
+    pub fn set_hislip_max_message_size(&self, kilobytes: u32) -> Result<()> {
+        self.set_attribute(
+            VI_ATTR_TCPIP_HISLIP_MAX_MESSAGE_KB,
+            kilobytes as ViAttrState,
+        )
+    }
+
+    /// Enables HiSLIP encryption (`VI_ATTR_TCPIP_HISLIP_ENCRYPTION_EN`).
+    ///
+    /// Fails with `AttributeNotSupported` on VISA implementations or
+    /// instruments without HiSLIP 2.0 security.
+    pub fn set_hislip_encryption_enabled(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_TCPIP_HISLIP_ENCRYPTION_EN, enabled as ViAttrState)
+    }
+
+    /// HiSLIP protocol version negotiated with the instrument, encoded as
+    /// VISA reports it (`VI_ATTR_TCPIP_HISLIP_VERSION`).
+    pub fn hislip_version(&self) -> Result<u32> {
+        self.get_attribute(VI_ATTR_TCPIP_HISLIP_VERSION)
+    }
+
+    /// Enables TCP keepalive packets (`VI_ATTR_TCPIP_KEEPALIVE`), detecting
+    /// a dropped LAN connection while the session is idle.
+    pub fn set_tcpip_keepalive(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_TCPIP_KEEPALIVE, enabled as ViAttrState)
+    }
+
+    /// Transfer protocol used by the session (`VI_ATTR_IO_PROT`).
+    pub fn io_protocol(&self) -> Result<IoProtocol> {
+        let protocol: ViUInt16 = self.get_attribute(VI_ATTR_IO_PROT)?;
+        IoProtocol::try_from(protocol)
+    }
+
+    pub fn set_io_protocol(&self, protocol: IoProtocol) -> Result<()> {
+        let protocol: ViUInt16 = protocol.into();
+        self.set_attribute(VI_ATTR_IO_PROT, protocol as ViAttrState)
+    }
+
+    /// Takes an exclusive lock on the resource.
+    ///
+    /// VISA has no separate HiSLIP lock call: on HiSLIP sessions `viLock` is
+    /// carried out with the HiSLIP `AsyncLock` messages, so the instrument
+    /// itself arbitrates the lock. Other sessions fall back to the generic
+    /// VISA lock, which only excludes sessions of the same VISA installation.
+    pub fn hislip_lock(&self, timeout: Timeout) -> Result<()> {
+        if !self.is_hislip().unwrap_or(false) {
+            debug!("Session is not HiSLIP, falling back to a generic lock");
+        }
+        self.lock(AccessMode::Exclusive, timeout)?;
+        Ok(())
+    }
+
+    /// Releases a lock taken with [`Session::hislip_lock`].
+    pub fn hislip_unlock(&self) -> Result<()> {
+        self.unlock()
+    }
+
+    /// Locks the resource, waiting up to `timeout` for other sessions to
+    /// release it.
+    ///
+    /// Returns the access key VISA generated for a shared lock, other
+    /// sessions pass it to [`Session::lock_shared_with_key`] to share the
+    /// lock. Exclusive locks return an empty string.
+    pub fn lock(&self, access: AccessMode, timeout: Timeout) -> Result<String> {
+        self.lock_with_key(access, None, timeout)
+    }
+
+    /// Takes a shared lock using the access key of an existing shared lock.
+    pub fn lock_shared_with_key(&self, key: &str, timeout: Timeout) -> Result<String> {
+        self.lock_with_key(AccessMode::Shared, Some(key), timeout)
+    }
+
+    fn lock_with_key(
+        &self,
+        access: AccessMode,
+        key: Option<&str>,
+        timeout: Timeout,
+    ) -> Result<String> {
+        let key = key
+            .map(CString::new)
+            .transpose()
+            .map_err(|_| Error::InvalidNullString)?;
+        let mut access_key = [0u8; 256];
+        let (requested_key, access_key_ptr) = match access {
+            AccessMode::Exclusive => (std::ptr::null(), std::ptr::null_mut()),
+            AccessMode::Shared => (
+                key.as_ref().map_or(std::ptr::null(), |key| key.as_ptr()),
+                access_key.as_mut_ptr(),
+            ),
+            AccessMode::None => return Err(Error::InvalidAccessMode),
+        };
+
         let completion_code = unsafe {
-            CompletionCode::try_from(viSetAttribute(
+            CompletionCode::try_from(viLock(
                 self.value,
-                VI_ATTR_TMO_VALUE,
+                access.into(),
                 timeout.try_into()?,
+                requested_key as _,
+                access_key_ptr as _,
+            ))?
+        };
+        debug!("Lock acquired with completion code: {}", completion_code);
+        self.lock_count.fetch_add(1, Ordering::AcqRel);
+
+        match access {
+            AccessMode::Shared => stringify_buffer(&access_key),
+            _ => Ok(String::new()),
+        }
+    }
+
+    /// Locks the resource until the returned guard is dropped.
+    pub fn lock_scoped(&self, access: AccessMode, timeout: Timeout) -> Result<LockGuard<'_>> {
+        let access_key = self.lock(access, timeout)?;
+        Ok(LockGuard {
+            session: self,
+            access_key,
+        })
+    }
+
+    /// Releases one lock taken by this session.
+    ///
+    /// Fails with `SessionNotLocked` without calling VISA if the session holds
+    /// no lock it acquired.
+    pub fn unlock(&self) -> Result<()> {
+        self.lock_count
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                count.checked_sub(1)
+            })
+            .map_err(|_| Error::SessionNotLocked)?;
+        let result = unsafe { CompletionCode::try_from(viUnlock(self.value)) };
+        let completion_code = result.inspect_err(|_| {
+            self.lock_count.fetch_add(1, Ordering::AcqRel);
+        })?;
+        debug!("Lock released with completion code: {}", completion_code);
+        Ok(())
+    }
+
+    /// Handle of the resource manager session this session was opened from.
+    pub fn resource_manager_handle(&self) -> Result<ViSession> {
+        self.get_attribute(VI_ATTR_RM_SESSION)
+    }
+
+    /// Describes a VISA status code using the parent resource manager session,
+    /// the raw code is appended in hexadecimal.
+    ///
+    /// Also works for vendor codes the crate does not recognize
+    /// (`InvalidCompletionCode`).
+    pub fn status_description(&self, status: ViStatus) -> Result<String> {
+        let resource_manager = self.resource_manager_handle()?;
+        utility::status_description(resource_manager, status)
+    }
+
+    /// Name VISA gives the interface instance, for example `GPIB0 (PCI-GPIB)`.
+    pub fn interface_instance_name(&self) -> Result<String> {
+        self.get_attribute_string(VI_ATTR_INTF_INST_NAME)
+    }
+
+    /// Reads an attribute as `i64`, sign extending the signed ones.
+    ///
+    /// `VI_ATTR_SLOT`, `VI_ATTR_VXI_LA`, `VI_ATTR_CMDR_LA`,
+    /// `VI_ATTR_MAINFRAME_LA`, `VI_ATTR_TRIG_ID`, `VI_ATTR_USB_INTFC_NUM` and
+    /// `VI_ATTR_USB_PROTOCOL` are sign extended from `ViInt16` (`-1` meaning
+    /// unknown or none), `VI_ATTR_STATUS` and `VI_ATTR_PXI_RECV_INTR_DATA`
+    /// from `ViInt32`. Every other attribute is unsigned and zero extended, so
+    /// `VI_TMO_INFINITE` reads as `0xFFFFFFFF`, only 64-bit values above
+    /// `i64::MAX` wrap negative.
+    pub fn get_attribute_i64(&self, attribute: ViAttr) -> Result<i64> {
+        let value = self.get_attribute_raw(attribute)?;
+        if SIGNED_16_BIT_ATTRIBUTES.contains(&attribute) {
+            Ok(narrow_attribute::<u16>(value)? as ViInt16 as i64)
+        } else if SIGNED_32_BIT_ATTRIBUTES.contains(&attribute) {
+            Ok(narrow_attribute::<u32>(value)? as ViInt32 as i64)
+        } else {
+            Ok(value as i64)
+        }
+    }
+
+    /// Manufacturer name reported by the interface (`VI_ATTR_MANF_NAME`),
+    /// available without a SCPI round trip.
+    pub fn manufacturer_name(&self) -> Result<String> {
+        self.get_attribute_string(VI_ATTR_MANF_NAME)
+    }
+
+    /// Model name reported by the interface (`VI_ATTR_MODEL_NAME`).
+    pub fn model_name(&self) -> Result<String> {
+        self.get_attribute_string(VI_ATTR_MODEL_NAME)
+    }
+
+    /// Name/value pairs of common attributes for diagnostics, attributes the
+    /// resource fails to report are left out.
+    pub fn attribute_snapshot(&self) -> Vec<(String, String)> {
+        let numeric = SNAPSHOT_ATTRIBUTES.iter().map(|(name, attribute)| {
+            let value = self.get_attribute_u32(*attribute);
+            (*name, value.map(|value| format!("{value:#X}")))
+        });
+        let strings = SNAPSHOT_STRING_ATTRIBUTES
+            .iter()
+            .map(|(name, attribute)| (*name, self.get_attribute_string(*attribute)));
+
+        numeric
+            .chain(strings)
+            .filter_map(|(name, value)| match value {
+                Ok(value) => Some((name.to_string(), value)),
+                Err(error) => {
+                    debug!("Attribute {} skipped in snapshot: {}", name, error);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Reads a 32-bit or narrower unsigned attribute.
+    ///
+    /// Fails with `AttributeNotSupported` if the resource lacks the attribute
+    /// and with `AttributeOverflow` if the value of a wider attribute does not
+    /// fit.
+    pub fn get_attribute_u32(&self, attribute: ViAttr) -> Result<u32> {
+        self.get_attribute(attribute)
+    }
+
+    pub fn set_attribute_u32(&self, attribute: ViAttr, value: u32) -> Result<()> {
+        self.set_attribute(attribute, value as ViAttrState)
+    }
+
+    /// Reads a boolean attribute, any non zero value of a wider attribute
+    /// being `true`.
+    pub fn get_attribute_bool(&self, attribute: ViAttr) -> Result<bool> {
+        let value = self.get_attribute_raw(attribute)?;
+        Ok(value != VI_FALSE as ViAttrState)
+    }
+
+    /// Reads a string attribute into a 256 byte buffer, the size VISA
+    /// guarantees for string attributes.
+    pub fn get_attribute_string(&self, attribute: ViAttr) -> Result<String> {
+        let mut buffer = [0u8; 256];
+        let completion_code = unsafe {
+            CompletionCode::try_from(viGetAttribute(
+                self.value,
+                attribute,
+                buffer.as_mut_ptr() as _,
             ))?
         };
-        debug!("Timeout set with completion code: {}", completion_code);
+        debug!(
+            "Attribute {:#X} read with completion code: {}",
+            attribute, completion_code
+        );
+        stringify_buffer(&buffer)
+    }
+
+    pub(crate) fn set_attribute(&self, attribute: ViAttr, value: ViAttrState) -> Result<()> {
+        let completion_code =
+            unsafe { CompletionCode::try_from(viSetAttribute(self.value, attribute, value))? };
+        debug!(
+            "Attribute {:#X} set with completion code: {}",
+            attribute, completion_code
+        );
         Ok(())
     }
 
+    /// Restores an attribute changed for a single operation, logging a
+    /// failure so the caller can report the operation's own error first.
+    fn restore_attribute(&self, attribute: ViAttr, value: ViAttrState) -> Result<()> {
+        self.set_attribute(attribute, value)
+            .inspect_err(|error| error!("Restoring attribute {:#X} failed: {}", attribute, error))
+    }
+
+    fn get_attribute<T: TryFrom<ViAttrState>>(&self, attribute: ViAttr) -> Result<T> {
+        narrow_attribute(self.get_attribute_raw(attribute)?)
+    }
+
+    /// Reads an attribute into a zeroed `ViAttrState`, wide enough for any
+    /// attribute VISA writes.
+    fn get_attribute_raw(&self, attribute: ViAttr) -> Result<ViAttrState> {
+        let mut value: ViAttrState = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viGetAttribute(
+                self.value,
+                attribute,
+                &mut value as *mut ViAttrState as _,
+            ))?
+        };
+        debug!(
+            "Attribute {:#X} read with completion code: {}",
+            attribute, completion_code
+        );
+        Ok(value)
+    }
+
+    /// Completion code of the last successful write, read, flush or clear.
+    ///
+    /// Warnings such as `DeviceNotPresent` or `MaximumCount` are otherwise
+    /// hidden behind the `Ok` result.
+    pub fn last_completion_code(&self) -> Option<CompletionCode> {
+        *self
+            .last_completion_code
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Number of bytes transferred by the last read, before any XON/XOFF
+    /// stripping. Also updated when the read failed part way.
+    pub fn last_read_count(&self) -> usize {
+        self.last_read_count.load(Ordering::Acquire)
+    }
+
+    /// Parity, framing and overrun errors VISA reported for this session since
+    /// the last call, clearing them.
+    ///
+    /// Tells a garbled transfer caused by the serial line apart from one
+    /// caused by the instrument.
+    pub fn serial_errors(&self) -> Result<SerialErrorFlags> {
+        let flags = self.serial_errors.swap(0, Ordering::AcqRel);
+        Ok(SerialErrorFlags::from_bits_truncate(flags))
+    }
+
+    fn record_completion_code(&self, status: ViStatus) -> Result<CompletionCode> {
+        let completion_code = CompletionCode::try_from(status).inspect_err(|error| {
+            let flag = match error {
+                Error::AsrlParity => SerialErrorFlags::PARITY,
+                Error::AsrlFraming => SerialErrorFlags::FRAMING,
+                Error::AsrlOverrun => SerialErrorFlags::OVERRUN,
+                _ => return,
+            };
+            self.serial_errors.fetch_or(flag.bits(), Ordering::AcqRel);
+        })?;
+        *self
+            .last_completion_code
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(completion_code);
+        Ok(completion_code)
+    }
+
     pub fn write<T: AsRef<str>>(&self, command: T) -> Result<()> {
+        self.write_bytes(command.as_ref().as_bytes())
+    }
+
+    /// Writes `command` followed by `terminator`, `\n` if `None`.
+    ///
+    /// The terminator only applies to this call, so sessions shared between
+    /// threads need no toggling of a session wide setting.
+    pub fn write_line<T: AsRef<str>>(&self, command: T, terminator: Option<&[u8]>) -> Result<()> {
+        let mut line = command.as_ref().as_bytes().to_vec();
+        line.extend_from_slice(terminator.unwrap_or(b"\n"));
+        self.write_bytes(&line)
+    }
+
+    /// Writes `template` with each `{}` replaced by the next argument in SCPI
+    /// format, for example
+    /// `session.write_template("SOUR:VOLT {};OUTP {}\n", &[&1.5, &true])`.
+    pub fn write_template(&self, template: &str, args: &[&dyn AsScpi]) -> Result<()> {
+        self.write(fill_template(template, args)?)
+    }
+
+    /// Sends an overlapped command, such as a sweep, and marks it pending.
+    ///
+    /// Until [`Session::wait_operation_complete`] or
+    /// `MandatoryCommands::operation_complete_blocking` confirms completion,
+    /// writes other than `*OPC`, `*OPC?`, `*WAI`, `*ESR?` and `*STB?` fail with
+    /// `Error::InProgress`, following the IEEE 488.2 overlapped command rules.
+    pub fn write_overlapped<T: AsRef<str>>(&self, command: T) -> Result<()> {
+        if self.operation_pending.load(Ordering::Acquire) {
+            return Err(Error::InProgress.in_operation("write"));
+        }
+        self.write(command)?;
+        self.operation_pending.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns `true` while an overlapped command awaits confirmation.
+    pub fn operation_pending(&self) -> bool {
+        self.operation_pending.load(Ordering::Acquire)
+    }
+
+    /// Blocks on `*OPC?` until the pending overlapped command completed.
+    ///
+    /// Fails with `InProgress` if the device answers that the operation is
+    /// not complete yet, the command then stays pending.
+    pub fn wait_operation_complete(&self) -> Result<()> {
+        if !self.operation_complete_query()? {
+            return Err(Error::InProgress.in_operation("*OPC? query"));
+        }
+        self.operation_pending.store(false, Ordering::Release);
+        Ok(())
+    }
+
+    fn is_completion_command(data: &[u8]) -> bool {
+        let data = data.trim_ascii_start();
+        COMPLETION_COMMANDS.iter().any(|command| {
+            data.get(..command.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(command))
+        })
+    }
+
+    /// Writes arbitrary bytes, for binary commands or manually built blocks.
+    ///
+    /// A `DeviceNotPresent` completion is reported as `Error::DeviceNotPresent`.
+    pub fn write_bytes(&self, data: &[u8]) -> Result<()> {
+        self.write_raw(data)
+            .and_then(|completion_code| match completion_code {
+                CompletionCode::DeviceNotPresent => Err(Error::DeviceNotPresent),
+                _ => Ok(()),
+            })
+            .map_err(|error| error.in_operation("write"))
+    }
+
+    fn write_raw(&self, command: &[u8]) -> Result<CompletionCode> {
+        let payload = checksum_payload(command, self.checksum);
+
+        let mut last_write = self
+            .last_write
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if let Some(elapsed) = last_write.map(|instant| instant.elapsed())
+            && elapsed < self.min_command_interval
+        {
+            std::thread::sleep(self.min_command_interval - elapsed);
+        }
+
+        let result = self.write_partial(&payload);
+        *last_write = Some(Instant::now());
+        result
+    }
+
+    /// Single `viWrite` of `data`, without checksum or command interval.
+    ///
+    /// Every write goes through here, so it also rejects writes while an
+    /// overlapped command is pending, see [`Session::write_overlapped`].
+    fn write_partial(&self, data: &[u8]) -> Result<CompletionCode> {
+        if self.operation_pending.load(Ordering::Acquire) && !Self::is_completion_command(data) {
+            return Err(Error::InProgress);
+        }
         let mut return_count: ViUInt32 = 0;
         let completion_code = unsafe {
-            CompletionCode::try_from(viWrite(
+            self.record_completion_code(viWrite(
                 self.value,
-                command.as_ref().as_ptr(),
-                command.as_ref().len() as ViUInt32,
+                data.as_ptr(),
+                data.len() as ViUInt32,
                 &mut return_count,
             ))?
         };
         debug!("Write completed with code: {}", completion_code);
 
-        if command.as_ref().len() != return_count as usize {
+        let length_reported = matches!(
+            completion_code,
+            CompletionCode::Success | CompletionCode::MaximumCount
+        );
+        if self.strict_write_length && length_reported && data.len() != return_count as usize {
             return Err(Error::WriteLengthMistmatch {
                 length: return_count as usize,
-                expected: command.as_ref().len(),
+                expected: data.len(),
             });
         }
 
+        Ok(completion_code)
+    }
+
+    /// Writes `prefix` followed by a definite length block of `len` bytes
+    /// streamed from `reader`, terminated by a newline.
+    ///
+    /// END is only sent with the final newline, the data is written in chunks
+    /// so large uploads do not have to be held in memory.
+    pub fn write_binary_block_from_reader(
+        &self,
+        prefix: &[u8],
+        reader: &mut impl Read,
+        len: usize,
+    ) -> Result<()> {
+        let send_end: ViBoolean = self.get_attribute(VI_ATTR_SEND_END_EN)?;
+        self.set_attribute(VI_ATTR_SEND_END_EN, VI_FALSE as ViAttrState)?;
+        let result = self.write_block_data(prefix, reader, len);
+        let restored = self.restore_attribute(VI_ATTR_SEND_END_EN, send_end as ViAttrState);
+        result?;
+        self.write_partial(b"\n")?;
+        restored
+    }
+
+    fn write_block_data(&self, prefix: &[u8], reader: &mut impl Read, len: usize) -> Result<()> {
+        let length = len.to_string();
+        let mut header = prefix.to_vec();
+        header.extend_from_slice(format!("#{}{}", length.len(), length).as_bytes());
+        self.write_partial(&header)?;
+
+        let mut buffer = vec![0u8; DEFAULT_CHUNK_SIZE];
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = &mut buffer[..remaining.min(DEFAULT_CHUNK_SIZE)];
+            reader.read_exact(chunk).map_err(Error::BlockSource)?;
+            self.write_partial(chunk)?;
+            remaining -= chunk.len();
+        }
         Ok(())
     }
 
+    /// Sets a checksum function whose result is appended to every command
+    /// sent with `write`, `write_bytes` or a query.
+    ///
+    /// Binary block uploads are sent without it and responses are not
+    /// checked. The checksum byte counts towards the written length, so with
+    /// [`Session::set_strict_write_length`] a write that stops short of it
+    /// fails with `WriteLengthMistmatch` like any other short write.
+    pub fn set_checksum(&mut self, checksum: Option<fn(&[u8]) -> u8>) {
+        self.checksum = checksum;
+    }
+
+    /// Sets the minimum time between the starts of consecutive writes,
+    /// `write` sleeps if needed. `Duration::ZERO` disables the limit.
+    ///
+    /// Meant for instruments that drop commands sent back to back.
+    pub fn set_min_command_interval(&mut self, interval: Duration) {
+        self.min_command_interval = interval;
+    }
+
+    /// Selects the VXI fast data channel used for transfers (0 to 7).
+    ///
+    /// FDC is only used once `VI_ATTR_IO_PROT` is set to `VI_PROT_FDC`.
+    pub fn set_fdc_channel(&self, channel: u16) -> Result<()> {
+        self.set_attribute(VI_ATTR_FDC_CHNL, channel as ViAttrState)
+    }
+
+    pub fn set_fdc_mode(&self, mode: FdcMode) -> Result<()> {
+        self.set_attribute(VI_ATTR_FDC_MODE, ViUInt16::from(mode) as ViAttrState)
+    }
+
+    /// Uses a channel pair, one per direction, instead of a single channel.
+    pub fn set_fdc_use_pair(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_FDC_USE_PAIR, enabled as ViAttrState)
+    }
+
+    /// Sets a delay applied after every completed read, giving the instrument
+    /// time to settle before the next command. `Duration::ZERO` disables it.
+    pub fn set_post_read_delay(&mut self, delay: Duration) {
+        self.post_read_delay = delay;
+    }
+
+    /// Allows or forbids DMA for block transfers (`VI_ATTR_DMA_ALLOW_EN`).
+    pub fn set_dma_allowed(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_DMA_ALLOW_EN, enabled as ViAttrState)
+    }
+
+    /// Enables the check that `write` transferred every byte (enabled by default).
+    ///
+    /// Some drivers report an inaccurate count for successful writes.
+    pub fn set_strict_write_length(&mut self, strict: bool) {
+        self.strict_write_length = strict;
+    }
+
     pub fn flush(&self, mode: FlushMode) -> Result<()> {
         let completion_code =
-            unsafe { CompletionCode::try_from(viFlush(self.value, mode.bits()))? };
+            unsafe { self.record_completion_code(viFlush(self.value, mode.bits()))? };
         debug!("Flush completed with code: {}", completion_code);
         Ok(())
     }
 
+    /// Sets the size of the VISA formatted I/O read buffer (`viSetBuf`).
+    ///
+    /// This buffer sits between the driver and VISA and bounds how much is
+    /// fetched from the OS per transfer, while the read chunk size set with
+    /// [`Session::set_read_chunk_size`] bounds each `viRead` call made by this
+    /// crate. Large responses over fast links benefit from raising both.
+    pub fn set_read_buffer_size(&self, bytes: u32) -> Result<()> {
+        let completion_code = unsafe {
+            CompletionCode::try_from(viSetBuf(self.value, VI_READ_BUF as ViUInt16, bytes))?
+        };
+        debug!(
+            "Read buffer resized with completion code: {}",
+            completion_code
+        );
+        Ok(())
+    }
+
+    /// Performs a device clear (USBTMC INITIATE_CLEAR, GPIB SDC, ...).
+    ///
+    /// This is the standard recovery step when the instrument's input buffer
+    /// is wedged and it no longer responds to `*CLS`, call it before retrying
+    /// a failed query sequence.
+    ///
+    /// The completion code is returned because some USBTMC drivers report a
+    /// warning instead of `Success` when the device did not complete the clear.
+    pub fn clear(&self) -> Result<CompletionCode> {
+        let completion_code = unsafe { self.record_completion_code(viClear(self.value))? };
+        self.operation_pending.store(false, Ordering::Release);
+        if completion_code != CompletionCode::Success {
+            warn!("Device clear completed with code: {}", completion_code);
+        } else {
+            debug!("Device clear completed with code: {}", completion_code);
+        }
+        Ok(completion_code)
+    }
+
+    /// Enables readdressing the device before every GPIB read and write
+    /// (`VI_ATTR_GPIB_READDR_EN`).
+    ///
+    /// Disabling it saves the addressing overhead when talking to a single
+    /// device in a tight loop.
+    pub fn set_gpib_readdress_enabled(&self, enabled: bool) -> Result<()> {
+        self.set_attribute(VI_ATTR_GPIB_READDR_EN, enabled as ViAttrState)
+    }
+
+    /// Sends a VXI word serial command and returns the response, `0` for modes
+    /// without one.
+    ///
+    /// Fails with `InvalidMode` or `OperationNotSupported` on non VXI sessions.
+    pub fn vxi_command_query(&self, mode: VxiCommandMode, command: u32) -> Result<u32> {
+        let mut response: ViUInt32 = 0;
+        let completion_code = unsafe {
+            CompletionCode::try_from(viVxiCommandQuery(
+                self.value,
+                mode.into(),
+                command,
+                &mut response,
+            ))?
+        };
+        debug!("VXI command query completed with code: {}", completion_code);
+        Ok(response)
+    }
+
+    /// Reads a response, failing with `Error::DeviceNotPresent` if VISA
+    /// reports the device as no longer present.
     pub fn read(&self) -> Result<String> {
-        let mut buffer = [0u8; 4096];
-        let mut output = vec![];
+        let output = self.read_bytes()?;
+        let output = String::from_utf8(output).map_err(|_| Error::InvalidUtf8)?;
 
-        loop {
+        Ok(output)
+    }
+
+    /// Reads a response as raw bytes, for binary payloads such as screenshots.
+    pub fn read_bytes(&self) -> Result<Vec<u8>> {
+        self.read_bytes_with_buffer_size(self.read_chunk_size)
+    }
+
+    /// Like [`Session::read`], requesting up to `size` bytes per `viRead` call.
+    pub fn read_with_buffer_size(&self, size: usize) -> Result<String> {
+        let output = self.read_bytes_with_buffer_size(size)?;
+        String::from_utf8(output).map_err(|_| Error::InvalidUtf8)
+    }
+
+    /// Like [`Session::read_bytes`], requesting up to `size` bytes per `viRead` call.
+    pub fn read_bytes_with_buffer_size(&self, size: usize) -> Result<Vec<u8>> {
+        self.read_raw(true, size)
+            .map_err(|error| error.in_operation("read"))
+    }
+
+    /// Limits the number of `viRead` calls a single read may take, guarding
+    /// against devices that return tiny chunks forever. `None` (the default)
+    /// removes the limit.
+    pub fn set_max_read_iterations(&mut self, limit: Option<usize>) {
+        self.max_read_iterations = limit;
+    }
+
+    /// Sets the number of bytes requested from each `viRead` call, 4096 by
+    /// default. Larger chunks mean fewer round trips on fast transfers.
+    pub fn set_read_chunk_size(&mut self, size: usize) {
+        self.read_chunk_size = size;
+    }
+
+    /// Reads an IEEE 488.2 arbitrary block response and returns its payload,
+    /// consuming the terminator that follows it.
+    pub fn read_binary_block(&self) -> Result<Vec<u8>> {
+        self.read_block_payload()
+            .map_err(|error| error.in_operation("block read"))
+    }
+
+    fn read_block_payload(&self) -> Result<Vec<u8>> {
+        let mut header = self.read_exact_raw(2)?;
+        let header = match header.as_slice() {
+            [b'#', b'0'] => BlockHeader::Indefinite,
+            [b'#', digits @ b'1'..=b'9'] => {
+                let digits = (digits - b'0') as usize;
+                header.extend(self.read_exact_raw(digits)?);
+                BlockHeader::try_from(header.as_slice())?
+            }
+            _ => {
+                return Err(Error::InvalidBlockHeader(
+                    String::from_utf8_lossy(&header).to_string(),
+                ));
+            }
+        };
+
+        let BlockHeader::Definite { length, .. } = header else {
+            let mut payload = self.read_raw(false, self.read_chunk_size)?;
+            if payload.ends_with(b"\n") {
+                payload.pop();
+            }
+            return Ok(payload);
+        };
+
+        let payload = self.read_exact_raw(length)?;
+        if self.last_completion_code() != Some(CompletionCode::Success) {
+            let terminator = self.read_raw(true, self.read_chunk_size)?;
+            debug!("Discarded {:?} after block", terminator);
+        }
+        Ok(payload)
+    }
+
+    /// Fails with `ReadLimitExceeded` once `iteration` reaches the limit set
+    /// with [`Session::set_max_read_iterations`].
+    fn check_read_iterations(&self, iteration: usize) -> Result<()> {
+        match self.max_read_iterations {
+            Some(limit) if iteration >= limit => Err(Error::ReadLimitExceeded(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads exactly `count` bytes, failing if END arrives first.
+    fn read_exact_raw(&self, count: usize) -> Result<Vec<u8>> {
+        let mut output = vec![0u8; count];
+        let mut filled = 0;
+
+        for iteration in 0.. {
+            if filled >= count {
+                break;
+            }
+            self.check_read_iterations(iteration)?;
             let mut return_count: ViUInt32 = 0;
             let completion_code = unsafe {
-                CompletionCode::try_from(viRead(
+                self.record_completion_code(viRead(
                     self.value,
-                    buffer.as_mut_ptr(),
-                    buffer.len() as ViUInt32,
+                    output[filled..].as_mut_ptr(),
+                    (count - filled) as ViUInt32,
                     &mut return_count,
                 ))?
             };
             debug!("Read completed with code: {}", completion_code);
 
+            filled += return_count as usize;
+            self.last_read_count.store(filled, Ordering::Release);
+            if completion_code == CompletionCode::Success && filled < count {
+                return Err(Error::IncompleteBlock {
+                    length: filled,
+                    expected: count,
+                });
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Reads until the device asserts END, ignoring the termination character.
+    ///
+    /// Suited to responses that may contain the termination character as data.
+    pub fn read_until_end(&self) -> Result<Vec<u8>> {
+        let enabled = self.termination_enabled()?;
+        self.set_attribute(VI_ATTR_TERMCHAR_EN, VI_FALSE as ViAttrState)?;
+        let output = self
+            .read_raw(false, self.read_chunk_size)
+            .map_err(|error| error.in_operation("read"));
+        let restored = self.restore_attribute(VI_ATTR_TERMCHAR_EN, enabled as ViAttrState);
+        let output = output?;
+        restored?;
+        Ok(output)
+    }
+
+    /// Sends `*IDN?` and returns the control characters ending the response,
+    /// for example `\r\n`.
+    ///
+    /// Termination character matching is disabled for the read, which runs
+    /// until the device asserts END or the timeout expires.
+    pub fn detect_terminator(&self) -> Result<Vec<u8>> {
+        let enabled = self.termination_enabled()?;
+        self.set_attribute(VI_ATTR_TERMCHAR_EN, VI_FALSE as ViAttrState)?;
+        let response = self.write_raw(b"*IDN?\n").and_then(|_| {
+            match self.read_raw(false, self.read_chunk_size) {
+                Err(Error::PartialRead { data }) => Ok(data),
+                result => result,
+            }
+        });
+        let restored = self.restore_attribute(VI_ATTR_TERMCHAR_EN, enabled as ViAttrState);
+
+        let response = response?;
+        restored?;
+        let start = response
+            .iter()
+            .rposition(|byte| !byte.is_ascii_control())
+            .map_or(0, |position| position + 1);
+        Ok(response[start..].to_vec())
+    }
+
+    /// Reads with `options` applied for this read only, the previous settings
+    /// are restored afterwards.
+    pub fn read_bytes_opts(&self, options: ReadOptions) -> Result<Vec<u8>> {
+        let suppress_end: ViBoolean = self.get_attribute(VI_ATTR_SUPPRESS_END_EN)?;
+        let termination_enabled: ViBoolean = self.get_attribute(VI_ATTR_TERMCHAR_EN)?;
+
+        let output = self
+            .apply_read_options(&options)
+            .and_then(|_| self.read_raw(true, options.chunk_size.unwrap_or(self.read_chunk_size)))
+            .map_err(|error| error.in_operation("read"));
+
+        let suppress_end_restored =
+            self.restore_attribute(VI_ATTR_SUPPRESS_END_EN, suppress_end as ViAttrState);
+        let termination_restored =
+            self.restore_attribute(VI_ATTR_TERMCHAR_EN, termination_enabled as ViAttrState);
+        let output = output?;
+        suppress_end_restored?;
+        termination_restored?;
+        Ok(output)
+    }
+
+    fn apply_read_options(&self, options: &ReadOptions) -> Result<()> {
+        if let Some(suppress_end) = options.suppress_end {
+            self.set_attribute(VI_ATTR_SUPPRESS_END_EN, suppress_end as ViAttrState)?;
+        }
+        if let Some(termination_enabled) = options.termination_enabled {
+            self.set_attribute(VI_ATTR_TERMCHAR_EN, termination_enabled as ViAttrState)?;
+        }
+        Ok(())
+    }
+
+    fn read_raw(&self, stop_on_termination: bool, chunk_size: usize) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; chunk_size.max(1)];
+        let mut output = vec![];
+
+        for iteration in 0.. {
+            self.check_read_iterations(iteration)?;
+            let mut return_count: ViUInt32 = 0;
+            let status = unsafe {
+                viRead(
+                    self.value,
+                    buffer.as_mut_ptr(),
+                    buffer.len() as ViUInt32,
+                    &mut return_count,
+                )
+            };
+
             output.extend_from_slice(&buffer[..return_count as usize]);
+            self.last_read_count.store(output.len(), Ordering::Release);
 
-            match completion_code {
-                CompletionCode::Success | CompletionCode::TerminationCharacterRead => {
-                    break;
+            let completion_code = match self.record_completion_code(status) {
+                Ok(completion_code) => completion_code,
+                Err(Error::Timeout) if !output.is_empty() => {
+                    return Err(Error::PartialRead { data: output });
                 }
-                CompletionCode::MaximumCount => continue,
+                Err(error) => return Err(error),
+            };
+            debug!("Read completed with code: {}", completion_code);
+
+            match completion_code {
+                CompletionCode::Success => break,
+                CompletionCode::TerminationCharacterRead if stop_on_termination => break,
+                CompletionCode::TerminationCharacterRead | CompletionCode::MaximumCount => continue,
+                CompletionCode::DeviceNotPresent => return Err(Error::DeviceNotPresent),
                 completion_code => return Err(Error::UnexpectedCompletionCode(completion_code)),
             }
         }
 
-        let output = String::from_utf8(output).map_err(|_| Error::InvalidUtf8)?;
+        if self.strip_xon_xoff {
+            let xon: u8 = self.get_attribute(VI_ATTR_ASRL_XON_CHAR)?;
+            let xoff: u8 = self.get_attribute(VI_ATTR_ASRL_XOFF_CHAR)?;
+            output.retain(|byte| *byte != xon && *byte != xoff);
+        }
+
+        if self.clear_mav_after_read {
+            let mut status: ViUInt16 = 0;
+            let completion_code =
+                unsafe { CompletionCode::try_from(viReadSTB(self.value, &mut status))? };
+            debug!(
+                "Status byte {:#04X} read with completion code: {}",
+                status, completion_code
+            );
+        }
+
+        if !self.post_read_delay.is_zero() {
+            std::thread::sleep(self.post_read_delay);
+        }
 
         Ok(output)
     }
@@ -105,6 +1218,409 @@ impl Session {
         self.write(command)?;
         self.read()
     }
+
+    /// Like [`Session::query`], returning the response as raw bytes without
+    /// UTF-8 conversion.
+    pub fn query_bytes<T: AsRef<str>>(&self, command: T) -> Result<Vec<u8>> {
+        self.write(command)?;
+        self.read_bytes()
+    }
+
+    /// Like [`Session::query`], terminating `command` as [`Session::write_line`] does.
+    pub fn query_line<T: AsRef<str>>(
+        &self,
+        command: T,
+        terminator: Option<&[u8]>,
+    ) -> Result<String> {
+        self.write_line(command, terminator)?;
+        self.read()
+    }
+
+    /// Queries a SCPI string response, removing the surrounding quotes.
+    pub fn query_string<T: AsRef<str>>(&self, command: T) -> Result<String> {
+        let response = self.query(command)?;
+        Ok(unquote(&response))
+    }
+
+    /// Queries a number, mapping the SCPI infinity and NaN sentinels to their
+    /// `f64` equivalents. An empty or `N/A` response fails with
+    /// `NumericParse`, use [`Session::query_scpi_number`] to accept `N/A`.
+    pub fn query_f64<T: AsRef<str>>(&self, command: T) -> Result<f64> {
+        parse_f64(&self.query(command)?)
+    }
+
+    /// Like [`Session::query_f64`], also returning the untrimmed response.
+    pub fn query_f64_raw<T: AsRef<str>>(&self, command: T) -> Result<(f64, String)> {
+        let response = self.query(command)?;
+        let value = parse_f64(&response)?;
+        Ok((value, response))
+    }
+
+    /// Queries a comma separated list of numbers, which some instruments wrap
+    /// in an arbitrary block header such as `#212 1.0,2.0,3.0`.
+    pub fn query_f64_list<T: AsRef<str>>(&self, command: T) -> Result<Vec<f64>> {
+        let response = self.query(command)?;
+        strip_list_block(&response)?
+            .split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(parse_f64)
+            .collect()
+    }
+
+    pub fn query_scpi_number<T: AsRef<str>>(&self, command: T) -> Result<ScpiNumber> {
+        let response = self.query(command)?;
+        ScpiNumber::try_from(response.as_str())
+    }
+
+    /// Queries `command` and parses the trimmed response, for example
+    /// `let volts: f64 = session.query_parse("MEAS:VOLT:DC?\n")?;`.
+    pub fn query_parse<T: FromStr, C: AsRef<str>>(&self, command: C) -> Result<T> {
+        let response = self.query(command)?;
+        let response = response.trim();
+        response.parse().map_err(|_| Error::ResponseParse {
+            response: response.to_string(),
+            type_name: std::any::type_name::<T>(),
+        })
+    }
+
+    /// Queries a comma separated list, parsing every trimmed field.
+    ///
+    /// An empty response or an empty trailing field yield no element.
+    pub fn query_list<T: FromStr, C: AsRef<str>>(&self, command: C) -> Result<Vec<T>> {
+        let response = self.query(command)?;
+        let response = response.trim();
+        let response = response.strip_suffix(',').unwrap_or(response);
+        if response.is_empty() {
+            return Ok(vec![]);
+        }
+
+        response
+            .split(',')
+            .map(|field| {
+                let field = field.trim();
+                field.parse().map_err(|_| Error::ResponseParse {
+                    response: field.to_string(),
+                    type_name: std::any::type_name::<T>(),
+                })
+            })
+            .collect()
+    }
+
+    /// Queries a response from a fixed set of tokens, such as `CV`/`CC`/`OFF`,
+    /// mapping the trimmed response with `parse`.
+    pub fn query_enum<T, C: AsRef<str>>(
+        &self,
+        command: C,
+        parse: impl Fn(&str) -> Option<T>,
+    ) -> Result<T> {
+        let response = self.query(command)?;
+        let response = response.trim();
+        parse(response).ok_or_else(|| Error::EnumParse(response.to_string()))
+    }
+
+    /// Fetches a list response page by page, sending
+    /// `<base_command> <offset>,<page_size>` until a page has fewer than
+    /// `page_size` items.
+    ///
+    /// Each page is a comma separated list whose items may be quoted strings,
+    /// see [`split_list`]. The items of all pages are returned in order, more
+    /// than 1024 pages fail with `ReadLimitExceeded`.
+    pub fn query_paged<T: AsRef<str>>(
+        &self,
+        base_command: T,
+        page_size: usize,
+    ) -> Result<Vec<String>> {
+        let base_command = base_command.as_ref().trim_end();
+        let mut items = vec![];
+
+        for _ in 0..PAGE_LIMIT {
+            let response =
+                self.query(format!("{} {},{}\n", base_command, items.len(), page_size))?;
+            let page = split_list(&response);
+            let complete = page.len() < page_size;
+            items.extend(page);
+            if complete {
+                return Ok(items);
+            }
+        }
+
+        Err(Error::ReadLimitExceeded(PAGE_LIMIT))
+    }
+
+    /// Queries a definite or indefinite length block and returns its payload.
+    ///
+    /// Termination character matching is disabled for the read so a newline
+    /// within the block cannot cut it short, the terminator following the
+    /// block is drained.
+    pub fn query_block<T: AsRef<str>>(&self, command: T) -> Result<Vec<u8>> {
+        self.write(command)
+            .and_then(|_| {
+                let enabled = self.termination_enabled()?;
+                self.set_attribute(VI_ATTR_TERMCHAR_EN, VI_FALSE as ViAttrState)?;
+                let payload = self.read_block_payload();
+                let restored = self.restore_attribute(VI_ATTR_TERMCHAR_EN, enabled as ViAttrState);
+                let payload = payload?;
+                restored?;
+                Ok(payload)
+            })
+            .map_err(|error| error.in_operation("block query"))
+    }
+
+    /// Queries `command`, decoding the response as an arbitrary block if it
+    /// starts with `#` and returning it unchanged otherwise.
+    ///
+    /// Covers instruments whose response format depends on a mode setting.
+    pub fn query_auto<T: AsRef<str>>(&self, command: T) -> Result<Vec<u8>> {
+        self.write(command)
+            .and_then(|_| self.read_auto_response())
+            .map_err(|error| error.in_operation("auto query"))
+    }
+
+    fn read_auto_response(&self) -> Result<Vec<u8>> {
+        let mut response = self.read_raw(true, self.read_chunk_size)?;
+        if response.first() != Some(&b'#') {
+            return Ok(response);
+        }
+
+        // The block data may contain the termination character, keep reading
+        // until the device asserts END or the definite length is covered.
+        let header = BlockHeader::try_from(response.as_slice())?;
+        let end = match header {
+            BlockHeader::Definite { length, .. } => header.size() + length,
+            BlockHeader::Indefinite => usize::MAX,
+        };
+        while response.len() <= end
+            && self.last_completion_code() == Some(CompletionCode::TerminationCharacterRead)
+        {
+            response.extend(self.read_raw(true, self.read_chunk_size)?);
+        }
+
+        Ok(parse_block(&response)?.to_vec())
+    }
+
+    /// Collects the status byte, the event status register and the pending
+    /// `SYSTem:ERRor?` entries.
+    ///
+    /// Reading the event status register and the error queue clears them. An
+    /// error queue that does not drain fails the snapshot, see
+    /// [`Session::read_error_queue`].
+    pub fn health_snapshot(&self) -> Result<HealthSnapshot> {
+        let status_byte = self.read_status_byte_query()?;
+        let event_status = self.standard_event_status_register_query()?;
+        let errors = self.read_error_queue()?;
+
+        Ok(HealthSnapshot {
+            status_byte,
+            event_status,
+            errors,
+        })
+    }
+
+    /// Drains the SCPI error queue (`SYSTem:ERRor?`) until it reports
+    /// `0,"No error"`, reading at most 128 entries.
+    ///
+    /// Fails with `ReadLimitExceeded` if the queue is not drained by then.
+    pub fn read_error_queue(&self) -> Result<Vec<ScpiError>> {
+        let mut errors = vec![];
+        for _ in 0..ERROR_QUEUE_LIMIT {
+            let response = self.query("SYSTem:ERRor?\n")?;
+            let error = ScpiError::try_from(response.as_str())?;
+            if error.code == 0 {
+                return Ok(errors);
+            }
+            errors.push(error);
+        }
+        Err(Error::ReadLimitExceeded(ERROR_QUEUE_LIMIT))
+    }
+
+    /// Measures the round trip time of an `*OPC?` query.
+    pub fn ping(&self) -> Result<Duration> {
+        let start = Instant::now();
+        self.operation_complete_query()?;
+        Ok(start.elapsed())
+    }
+
+    /// Runs `f` while holding the session's critical section mutex.
+    ///
+    /// Threads sharing the session that wrap their compound operations in
+    /// this call cannot interleave a write of one with the read of another.
+    /// `f` must not call `critical_section` again.
+    pub fn critical_section<T>(&self, f: impl FnOnce(&Session) -> Result<T>) -> Result<T> {
+        let _guard = self
+            .critical_section
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        f(self)
+    }
+
+    /// Writes `command` between `*CLS` and `*ESR?`, failing if the instrument
+    /// flagged a command or execution error.
+    pub fn send_checked<T: AsRef<str>>(&self, command: T) -> Result<()> {
+        self.clear_status()?;
+        self.write(command)?;
+        let register = self.standard_event_status_register_query()?;
+        let rejected = register.intersection(
+            StandardEventStatusRegister::COMMAND_ERROR
+                | StandardEventStatusRegister::EXECUTION_ERROR,
+        );
+        if !rejected.is_empty() {
+            return Err(Error::CommandRejected(rejected));
+        }
+        Ok(())
+    }
+
+    /// Applies `(command, verify query, expected response)` steps in order.
+    ///
+    /// Stops at the first step whose trimmed response differs from the
+    /// expected value, reporting the index of the failing step.
+    pub fn transaction(&self, steps: &[(&str, &str, &str)]) -> Result<()> {
+        for (step, (command, query, expected)) in steps.iter().enumerate() {
+            let response = self
+                .write(command)
+                .and_then(|_| self.query(query))
+                .map_err(|error| Error::TransactionFailed {
+                    step,
+                    source: Box::new(error),
+                })?;
+
+            if response.trim() != expected.trim() {
+                return Err(Error::TransactionMismatch {
+                    step,
+                    expected: expected.to_string(),
+                    actual: response.trim().to_string(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes `data` and reads the response back.
+    ///
+    /// Against an echo capable device this verifies the terminator handling of
+    /// both directions, the returned bytes include whatever terminator was read.
+    /// Errors are reported like those of [`Session::write_bytes`] and
+    /// [`Session::read_bytes`]. Exercising it needs an echo capable instrument,
+    /// so it is not covered by the unit tests.
+    pub fn echo_roundtrip(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.write_bytes(data)?;
+        self.read_bytes()
+    }
+
+    /// Queries `command`, retrying on timeout until `deadline` has passed.
+    ///
+    /// The write and the read of each attempt use the session timeout capped
+    /// to the time remaining, the session timeout is restored afterwards. The
+    /// device is cleared before a retry so a late reply is not mistaken for
+    /// the answer to the next attempt.
+    pub fn query_until<T: AsRef<str>>(&self, command: T, deadline: Instant) -> Result<String> {
+        let timeout: ViUInt32 = self.get_attribute(VI_ATTR_TMO_VALUE)?;
+        let result = self.query_within(
+            command.as_ref(),
+            deadline,
+            Duration::from_millis(timeout as u64),
+        );
+        let restored = self.restore_attribute(VI_ATTR_TMO_VALUE, timeout as ViAttrState);
+        let response = result?;
+        restored?;
+        Ok(response)
+    }
+
+    fn query_within(&self, command: &str, deadline: Instant, timeout: Duration) -> Result<String> {
+        loop {
+            let result = self
+                .apply_deadline(deadline, timeout)
+                .and_then(|_| self.write(command))
+                .and_then(|_| self.apply_deadline(deadline, timeout))
+                .and_then(|_| self.read());
+            match result {
+                Err(error) if matches!(error.root_cause(), Error::Timeout) => {
+                    if Instant::now() >= deadline {
+                        return Err(error);
+                    }
+                    debug!("Query timed out, clearing the device and retrying until deadline");
+                    // A late reply to this attempt must not answer the next one.
+                    self.clear()?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Caps the session timeout to the time left until `deadline`, failing
+    /// with `Timeout` once it has passed.
+    fn apply_deadline(&self, deadline: Instant, timeout: Duration) -> Result<()> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::Timeout);
+        }
+        self.set_timeout(Timeout::Custom(remaining.min(timeout)))
+    }
+}
+
+/// Lock taken with [`Session::lock_scoped`], released on drop.
+#[derive(Debug)]
+pub struct LockGuard<'a> {
+    session: &'a Session,
+    access_key: String,
+}
+
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(error) = self.session.unlock() {
+            error!("Releasing scoped lock failed with code: {}", error);
+        }
+    }
+}
+
+impl LockGuard<'_> {
+    /// Access key of a shared lock, empty for exclusive locks.
+    pub fn access_key(&self) -> &str {
+        &self.access_key
+    }
+}
+
+impl Deref for LockGuard<'_> {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        self.session
+    }
+}
+
+/// Reads with a single `viRead` per call.
+///
+/// After a read that ended with END the next call returns `0`, so
+/// `read_to_end` stops at the end of the message instead of timing out.
+impl Read for Session {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if std::mem::take(&mut self.end_reached) {
+            return Ok(0);
+        }
+
+        let mut return_count: ViUInt32 = 0;
+        let status = unsafe {
+            viRead(
+                self.value,
+                buf.as_mut_ptr(),
+                buf.len() as ViUInt32,
+                &mut return_count,
+            )
+        };
+        self.last_read_count
+            .store(return_count as usize, Ordering::Release);
+
+        match self.record_completion_code(status) {
+            Ok(completion_code) => {
+                debug!("Read completed with code: {}", completion_code);
+                self.end_reached = completion_code == CompletionCode::Success;
+                Ok(return_count as usize)
+            }
+            Err(Error::Timeout) if return_count > 0 => Ok(return_count as usize),
+            Err(error) => Err(error.into()),
+        }
+    }
 }
 
 impl MandatoryCommands for Session {
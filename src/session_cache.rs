@@ -0,0 +1,141 @@
+use crate::{
+    error::*,
+    resource_manager::ResourceManager,
+    session::Session,
+    utility::{AccessMode, Timeout},
+};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, PoisonError, Weak},
+};
+use tracing::debug;
+
+type Configure = Box<dyn Fn(&mut Session) -> Result<()> + Send + Sync>;
+
+/// Cached session of a resource, locked while the session is being opened.
+type Slot = Arc<Mutex<Weak<Session>>>;
+
+/// Pool of open sessions keyed by resource name.
+///
+/// Sessions are handed out as `Arc<Session>`, the underlying VISA session is
+/// closed once the last clone is dropped. Settings taking `&mut Session`,
+/// such as [`Session::set_checksum`] or [`Session::set_read_chunk_size`],
+/// cannot be changed on a shared session, apply them with
+/// [`SessionCache::set_configure`] when the session is opened.
+pub struct SessionCache {
+    resource_manager: ResourceManager,
+    access_mode: AccessMode,
+    timeout: Timeout,
+    configure: Option<Configure>,
+    sessions: Mutex<HashMap<String, Slot>>,
+}
+
+impl fmt::Debug for SessionCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionCache")
+            .field("resource_manager", &self.resource_manager)
+            .field("access_mode", &self.access_mode)
+            .field("timeout", &self.timeout)
+            .field("sessions", &self.sessions)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SessionCache {
+    pub fn new(
+        resource_manager: ResourceManager,
+        access_mode: AccessMode,
+        timeout: Timeout,
+    ) -> Self {
+        Self {
+            resource_manager,
+            access_mode,
+            timeout,
+            configure: None,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets a function applied to every session the cache opens, before it
+    /// is shared. A failure closes the session again.
+    pub fn set_configure(
+        &mut self,
+        configure: impl Fn(&mut Session) -> Result<()> + Send + Sync + 'static,
+    ) {
+        self.configure = Some(Box::new(configure));
+    }
+
+    pub fn resource_manager(&self) -> &ResourceManager {
+        &self.resource_manager
+    }
+
+    /// Returns the open session for `resource`, opening a new one if none is alive.
+    ///
+    /// Only lookups of the same resource wait for a session being opened.
+    pub fn get_or_open<T: AsRef<str>>(&self, resource: T) -> Result<Arc<Session>> {
+        let key = Self::key(resource.as_ref());
+        let slot = {
+            let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+            sessions.retain(|name, slot| {
+                *name == key
+                    || Arc::strong_count(slot) > 1
+                    || slot
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .strong_count()
+                        > 0
+            });
+            Arc::clone(sessions.entry(key).or_default())
+        };
+        let mut cached = slot.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(session) = cached.upgrade() {
+            debug!("Reusing cached session for resource {}", resource.as_ref());
+            return Ok(session);
+        }
+
+        let mut session = self.resource_manager.open_session(
+            resource.as_ref(),
+            self.access_mode,
+            self.timeout,
+        )?;
+        if let Some(configure) = &self.configure {
+            configure(&mut session)?;
+        }
+        let session = Arc::new(session);
+        *cached = Arc::downgrade(&session);
+
+        Ok(session)
+    }
+
+    /// Returns `true` if a live session for `resource` is held by the cache.
+    pub fn contains<T: AsRef<str>>(&self, resource: T) -> bool {
+        let key = Self::key(resource.as_ref());
+        let slot = self
+            .sessions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&key)
+            .cloned();
+        slot.is_some_and(|slot| {
+            slot.lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .strong_count()
+                > 0
+        })
+    }
+
+    /// Finds the resources matching `expression` that have no live session in
+    /// the cache.
+    pub fn find_available_resources<T: AsRef<str>>(&self, expression: T) -> Result<Vec<String>> {
+        let mut resources = self.resource_manager.find_resources(expression)?;
+        resources.retain(|resource| !self.contains(resource));
+        Ok(resources)
+    }
+
+    /// Resource names are case insensitive in VISA.
+    fn key(resource: &str) -> String {
+        resource.trim().to_ascii_uppercase()
+    }
+}
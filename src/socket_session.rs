@@ -0,0 +1,39 @@
+use crate::{error::*, session::Session, utility::MandatoryCommands};
+use std::ops::Deref;
+use visa_bindings::*;
+
+/// Session to a raw TCP/IP socket resource.
+///
+/// Raw socket instruments have no END indicator, so reads stop on `\n`.
+/// `VI_ATTR_SEND_END_EN` keeps its default, every write flushes the send
+/// buffer.
+#[derive(Debug)]
+pub struct SocketSession {
+    session: Session,
+}
+
+impl SocketSession {
+    pub(crate) fn new(session: Session) -> Result<Self> {
+        session.set_attribute(VI_ATTR_TERMCHAR, b'\n' as ViAttrState)?;
+        session.set_attribute(VI_ATTR_TERMCHAR_EN, VI_TRUE as ViAttrState)?;
+        Ok(Self { session })
+    }
+
+    pub fn into_inner(self) -> Session {
+        self.session
+    }
+}
+
+impl Deref for SocketSession {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl MandatoryCommands for SocketSession {
+    fn as_session(&self) -> &Session {
+        &self.session
+    }
+}
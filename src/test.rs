@@ -1,7 +1,18 @@
 use crate::test::instrument::Instrument;
-use crate::{resource_manager::ResourceManager, utility::MandatoryCommands};
+use crate::{
+    error::{CompletionCode, Error},
+    resource_manager::ResourceManager,
+    resource_name::{InterfaceType, ResourceAddress, ResourceName},
+    session::{checksum_payload, narrow_attribute},
+    utility::{
+        AccessMode, FlushMode, MandatoryCommands, ScpiError, ScpiNumber, ServiceRequestEnable,
+        StatusByteRegister, Timeout, fill_template, parse_block, parse_f64, parse_scpi_version,
+        split_list, strip_list_block, unquote,
+    },
+};
 use color_eyre::{Result, eyre::eyre};
 use tracing::{info, level_filters::LevelFilter};
+use visa_bindings::*;
 
 mod instrument {
     use crate::{
@@ -132,3 +143,333 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn lock_guard() -> Result<()> {
+    let resource_manager = ResourceManager::new()?;
+    let resource = resource_manager
+        .find_resources("?*INSTR")?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("At least one valid instrument resource is required for testing"))?;
+    let session = resource_manager.open_session(resource, AccessMode::None, Timeout::Immediate)?;
+
+    let guard = session.lock_scoped(AccessMode::Exclusive, Timeout::Immediate)?;
+    assert!(guard.is_locked());
+    drop(guard);
+    assert!(!session.is_locked());
+
+    session.lock(AccessMode::Exclusive, Timeout::Immediate)?;
+    session.unlock()?;
+    Ok(())
+}
+
+#[test]
+fn attribute_width() -> Result<()> {
+    let resource_manager = ResourceManager::new()?;
+    let resource = resource_manager
+        .find_resources("?*INSTR")?
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("At least one valid instrument resource is required for testing"))?;
+    let session = resource_manager.open_session(resource, AccessMode::None, Timeout::Immediate)?;
+
+    // 64-bit attribute read as u32 and 32-bit attribute read as bool.
+    session.set_timeout(Timeout::Custom(std::time::Duration::from_millis(1500)))?;
+    assert!(session.get_attribute_u32(VI_ATTR_USER_DATA).is_ok());
+    assert!(session.get_attribute_bool(VI_ATTR_TMO_VALUE)?);
+    assert_eq!(session.get_attribute_u32(VI_ATTR_TMO_VALUE)?, 1500);
+    Ok(())
+}
+
+#[test]
+fn narrow_attribute_value() {
+    assert_eq!(narrow_attribute::<u16>(1).ok(), Some(1));
+    assert_eq!(narrow_attribute::<u32>(0xFFFF_FFFF).ok(), Some(u32::MAX));
+    assert!(narrow_attribute::<u32>(0x1_0000_0000).is_err());
+    assert!(narrow_attribute::<u16>(0x1_0000).is_err());
+    assert!(narrow_attribute::<u8>(0x100).is_err());
+}
+
+#[test]
+fn status_codes() {
+    let errors = [
+        (VI_ERROR_SYSTEM_ERROR, "System"),
+        (VI_ERROR_TMO, "Timeout"),
+        (VI_ERROR_RSRC_NFOUND, "ResourceNotFound"),
+        (VI_ERROR_CONN_LOST, "ConnectionLost"),
+        (VI_ERROR_NPERMISSION, "NoPermission"),
+    ];
+    for (status, name) in errors {
+        let error = CompletionCode::try_from(status).unwrap_err();
+        assert_eq!(format!("{error:?}"), name);
+    }
+
+    let codes = [
+        (VI_SUCCESS, CompletionCode::Success),
+        (
+            VI_SUCCESS_TERM_CHAR,
+            CompletionCode::TerminationCharacterRead,
+        ),
+        (VI_SUCCESS_MAX_CNT, CompletionCode::MaximumCount),
+        (
+            VI_SUCCESS_SYNC,
+            CompletionCode::AsynchronousOperationHandledSynchronously,
+        ),
+        (
+            VI_WARN_EXT_FUNC_NIMPL,
+            CompletionCode::ExtendedFunctionNotImplemented,
+        ),
+    ];
+    for (status, code) in codes {
+        assert_eq!(
+            CompletionCode::try_from(status as ViStatus).ok(),
+            Some(code)
+        );
+    }
+    assert!(matches!(
+        CompletionCode::try_from(0x3FFF_FFFF),
+        Err(Error::InvalidCompletionCode(0x3FFF_FFFF))
+    ));
+}
+
+#[test]
+fn checksum() {
+    fn sum(data: &[u8]) -> u8 {
+        data.iter().fold(0, |sum, byte| sum.wrapping_add(*byte))
+    }
+
+    assert_eq!(&*checksum_payload(b"*RST", None), b"*RST");
+    assert_eq!(&*checksum_payload(b"AB", Some(sum)), b"AB\x83");
+    assert_eq!(&*checksum_payload(b"", Some(sum)), b"\x00");
+}
+
+#[test]
+fn block() -> Result<()> {
+    assert_eq!(parse_block(b"#15hello\n")?, b"hello");
+    assert_eq!(parse_block(b"#2100123456789")?, b"0123456789");
+    assert_eq!(parse_block(b"#0line\nbreak\n")?, b"line\nbreak");
+    assert!(parse_block(b"#19abc").is_err());
+    assert!(parse_block(b"#A12").is_err());
+    assert!(parse_block(b"5hello").is_err());
+    Ok(())
+}
+
+#[test]
+fn template() -> Result<()> {
+    assert_eq!(
+        fill_template("SOUR:VOLT {};OUTP {}", &[&1.5, &true])?,
+        "SOUR:VOLT 1.5E0;OUTP ON"
+    );
+    assert_eq!(
+        fill_template("DISP:TEXT {}", &[&"say \"hi\""])?,
+        "DISP:TEXT \"say \"\"hi\"\"\""
+    );
+    assert_eq!(
+        fill_template("TRIG:COUN {}", &[&f64::INFINITY])?,
+        "TRIG:COUN 9.9E37"
+    );
+    assert!(fill_template("SOUR:VOLT {}", &[]).is_err());
+    Ok(())
+}
+
+#[test]
+fn scpi_number() -> Result<()> {
+    let numbers = [
+        ("1.5E0", ScpiNumber::Value(1.5)),
+        (" -2e-3\n", ScpiNumber::Value(-2e-3)),
+        ("9.9E37", ScpiNumber::Infinity),
+        (" +9.90000E+37\r\n", ScpiNumber::Infinity),
+        ("-9.9E37\n", ScpiNumber::NegativeInfinity),
+        ("\t9.91E37 ", ScpiNumber::NotANumber),
+        ("INF\n", ScpiNumber::Infinity),
+        (" -inf", ScpiNumber::NegativeInfinity),
+        ("NAN\n", ScpiNumber::NotANumber),
+        (" N/A ", ScpiNumber::NotAvailable),
+    ];
+    for (response, number) in numbers {
+        assert_eq!(ScpiNumber::try_from(response)?, number, "{response:?}");
+    }
+    assert!(matches!(
+        ScpiNumber::try_from("\n"),
+        Err(Error::NumericParse(_))
+    ));
+    assert!(matches!(parse_f64(" N/A\n"), Err(Error::NumericParse(_))));
+    assert_eq!(parse_f64("9.9E37")?, f64::INFINITY);
+    assert!(ScpiNumber::try_from("MAX").is_err());
+    assert!(ScpiNumber::try_from("MINimum").is_err());
+    Ok(())
+}
+
+#[test]
+fn scpi_error() -> Result<()> {
+    let errors = [
+        ("0,\"No error\"\n", 0, "No error"),
+        ("-113,\"Undefined header\"", -113, "Undefined header"),
+        (
+            " -222 , \"Data out of range;\"\"VOLT\"\"\" ",
+            -222,
+            "Data out of range;\"VOLT\"",
+        ),
+        ("+100,\"Device, specific\"", 100, "Device, specific"),
+    ];
+    for (response, code, message) in errors {
+        let error = ScpiError::try_from(response)?;
+        assert_eq!(
+            (error.code, error.message.as_str()),
+            (code, message),
+            "{response:?}"
+        );
+    }
+    assert!(ScpiError::try_from("No error").is_err());
+    assert!(ScpiError::try_from("E1,\"Bad code\"").is_err());
+    Ok(())
+}
+
+#[test]
+fn unquote_string() {
+    let strings = [
+        ("\"hello\"", "hello"),
+        (" \"say \"\"hi\"\"\"\n", "say \"hi\""),
+        ("\"\"", ""),
+        ("bare", "bare"),
+        ("\"unterminated", "\"unterminated"),
+    ];
+    for (value, unquoted) in strings {
+        assert_eq!(unquote(value), unquoted, "{value:?}");
+    }
+}
+
+#[test]
+fn list_split() {
+    let lists = [
+        ("\n", vec![]),
+        ("1,2,3\n", vec!["1", "2", "3"]),
+        ("\"A\",\"B\"", vec!["A", "B"]),
+        (
+            "\"DATA.CSV,ASC,1024\",\"a \"\"b\"\"\"\n",
+            vec!["DATA.CSV,ASC,1024", "a \"b\""],
+        ),
+        (" VOLT , \"x\" ", vec!["VOLT", "x"]),
+    ];
+    for (response, items) in lists {
+        assert_eq!(split_list(response), items, "{response:?}");
+    }
+}
+
+#[test]
+fn scpi_version() -> Result<()> {
+    assert_eq!(parse_scpi_version("1999.0\n")?, (1999, 0));
+    assert_eq!(parse_scpi_version(" 1994.2 ")?, (1994, 2));
+    assert!(parse_scpi_version("1999").is_err());
+    assert!(parse_scpi_version("SCPI.0").is_err());
+    Ok(())
+}
+
+#[test]
+fn service_request_enable() {
+    let masks = [
+        (StatusByteRegister::empty(), ServiceRequestEnable::empty()),
+        (
+            StatusByteRegister::MESSAGE_AVAILABLE,
+            ServiceRequestEnable::MESSAGE_AVAILABLE,
+        ),
+        (
+            StatusByteRegister::QUESTIONABLE_STATUS_SUMMARY
+                | StatusByteRegister::EVENT_STATUS_BIT
+                | StatusByteRegister::OPERATION_STATUS_SUMMARY,
+            ServiceRequestEnable::QUESTIONABLE_STATUS
+                | ServiceRequestEnable::EVENT_STATUS
+                | ServiceRequestEnable::OPERATION_STATUS,
+        ),
+    ];
+    for (status, enable) in masks {
+        assert_eq!(ServiceRequestEnable::from_status_bits(status), enable);
+        assert_eq!(enable.status_bits(), status);
+    }
+    assert_eq!(
+        ServiceRequestEnable::from_status_bits(StatusByteRegister::all()).status_bits(),
+        StatusByteRegister::QUESTIONABLE_STATUS_SUMMARY
+            | StatusByteRegister::MESSAGE_AVAILABLE
+            | StatusByteRegister::EVENT_STATUS_BIT
+            | StatusByteRegister::OPERATION_STATUS_SUMMARY
+    );
+}
+
+#[test]
+fn list_block() -> Result<()> {
+    let lists = [
+        ("1.0,2.0\n", "1.0,2.0"),
+        ("#211 1.0,2.0,3\n", " 1.0,2.0,3"),
+        ("#15 1,2\n", " 1,2"),
+        ("#01,2,3\n", "1,2,3"),
+    ];
+    for (response, list) in lists {
+        assert_eq!(strip_list_block(response)?, list, "{response:?}");
+    }
+    assert!(strip_list_block("#A1,2").is_err());
+    Ok(())
+}
+
+#[test]
+fn resource_name() -> Result<()> {
+    let usb: ResourceName = "USB0::0x1234::0x5678::SERIAL::INSTR".parse()?;
+    assert_eq!(usb.interface_type, InterfaceType::Usb);
+    assert_eq!(
+        usb.address,
+        ResourceAddress::Usb {
+            manufacturer_id: 0x1234,
+            model_code: 0x5678,
+            serial_number: "SERIAL".to_string(),
+            interface_number: None,
+        }
+    );
+
+    let tcpip: ResourceName = "TCPIP0::192.168.0.5::inst0::INSTR".parse()?;
+    assert_eq!(
+        tcpip.address,
+        ResourceAddress::Tcpip {
+            host: "192.168.0.5".to_string(),
+            device_name: Some("inst0".to_string()),
+        }
+    );
+
+    let gpib: ResourceName = "GPIB0::14::INSTR".parse()?;
+    assert_eq!(gpib.interface_type, InterfaceType::Gpib);
+    assert_eq!(
+        gpib.address,
+        ResourceAddress::Gpib {
+            primary_address: 14,
+            secondary_address: None,
+        }
+    );
+
+    let asrl: ResourceName = "ASRL3::INSTR".parse()?;
+    assert_eq!(asrl.board, 3);
+    assert_eq!(asrl.address, ResourceAddress::None);
+
+    assert!("FOO0::INSTR".parse::<ResourceName>().is_err());
+    assert!("GPIB0::x::INSTR".parse::<ResourceName>().is_err());
+    Ok(())
+}
+
+#[test]
+fn flush_mode() {
+    let modes = [
+        (FlushMode::READ_BUFFER, VI_READ_BUF),
+        (FlushMode::READ_BUFFER_DISCARD, VI_READ_BUF_DISCARD),
+        (FlushMode::WRITE_BUFFER, VI_WRITE_BUF),
+        (FlushMode::WRITE_BUFFER_DISCARD, VI_WRITE_BUF_DISCARD),
+        (FlushMode::IO_INPUT_BUFFER, VI_IO_IN_BUF),
+        (FlushMode::IO_INPUT_BUFFER_DISCARD, VI_IO_IN_BUF_DISCARD),
+        (FlushMode::IO_OUTPUT_BUFFER, VI_IO_OUT_BUF),
+        (FlushMode::IO_OUTPUT_BUFFER_DISCARD, VI_IO_OUT_BUF_DISCARD),
+    ];
+
+    for (index, (mode, constant)) in modes.iter().enumerate() {
+        assert_eq!(mode.bits() as u32, *constant);
+        for (other, _) in &modes[index + 1..] {
+            assert_ne!(mode.bits(), other.bits());
+        }
+    }
+}
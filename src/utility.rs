@@ -1,8 +1,19 @@
+pub use crate::protocol::*;
 use crate::{error::*, session::Session};
 use bitflags::bitflags;
-use std::{ffi::CStr, time::Duration};
+use std::{
+    ffi::CStr,
+    sync::atomic::Ordering,
+    thread,
+    time::{Duration, Instant},
+};
+use tracing::debug;
 use visa_bindings::*;
 
+/// Interval between `*ESR?` polls in
+/// [`MandatoryCommands::operation_complete_blocking`].
+const OPERATION_COMPLETE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Timeout {
     Immediate,
@@ -26,6 +37,17 @@ impl TryFrom<Timeout> for ViUInt32 {
     }
 }
 
+impl From<ViUInt32> for Timeout {
+    fn from(value: ViUInt32) -> Self {
+        match value {
+            0 => Timeout::Immediate,
+            0xFFFFFFFE => Timeout::Maximum,
+            0xFFFFFFFF => Timeout::Infinite,
+            milliseconds => Timeout::Custom(Duration::from_millis(milliseconds.into())),
+        }
+    }
+}
+
 impl TryFrom<Timeout> for ViAttrState {
     type Error = Error;
     fn try_from(value: Timeout) -> Result<Self> {
@@ -51,6 +73,250 @@ impl From<AccessMode> for ViAccessMode {
     }
 }
 
+impl TryFrom<ViAccessMode> for AccessMode {
+    type Error = Error;
+    fn try_from(value: ViAccessMode) -> Result<Self> {
+        match value {
+            VI_EXCLUSIVE_LOCK => Ok(AccessMode::Exclusive),
+            VI_SHARED_LOCK => Ok(AccessMode::Shared),
+            VI_NO_LOCK => Ok(AccessMode::None),
+            _ => Err(Error::InvalidAccessMode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    Mark,
+    Space,
+}
+
+impl From<Parity> for ViUInt16 {
+    fn from(value: Parity) -> Self {
+        let value = match value {
+            Parity::None => VI_ASRL_PAR_NONE,
+            Parity::Odd => VI_ASRL_PAR_ODD,
+            Parity::Even => VI_ASRL_PAR_EVEN,
+            Parity::Mark => VI_ASRL_PAR_MARK,
+            Parity::Space => VI_ASRL_PAR_SPACE,
+        };
+        value as ViUInt16
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    OneAndHalf,
+    Two,
+}
+
+impl From<StopBits> for ViUInt16 {
+    fn from(value: StopBits) -> Self {
+        let value = match value {
+            StopBits::One => VI_ASRL_STOP_ONE,
+            StopBits::OneAndHalf => VI_ASRL_STOP_ONE5,
+            StopBits::Two => VI_ASRL_STOP_TWO,
+        };
+        value as ViUInt16
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowControl {
+    None,
+    XonXoff,
+    RtsCts,
+    DtrDsr,
+}
+
+impl From<FlowControl> for ViUInt16 {
+    fn from(value: FlowControl) -> Self {
+        let value = match value {
+            FlowControl::None => VI_ASRL_FLOW_NONE,
+            FlowControl::XonXoff => VI_ASRL_FLOW_XON_XOFF,
+            FlowControl::RtsCts => VI_ASRL_FLOW_RTS_CTS,
+            FlowControl::DtrDsr => VI_ASRL_FLOW_DTR_DSR,
+        };
+        value as ViUInt16
+    }
+}
+
+/// Serial line settings, defaults to 9600-8-N-1 without flow control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialConfig {
+    pub baud_rate: u32,
+    /// 5 to 8 data bits.
+    pub data_bits: u8,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+}
+
+impl Default for SerialConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: 9600,
+            data_bits: 8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+        }
+    }
+}
+
+/// Serial end of message mode (`VI_ATTR_ASRL_END_IN`/`VI_ATTR_ASRL_END_OUT`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerialEnd {
+    None,
+    /// The last data bit of a byte marks the end.
+    LastBit,
+    /// The termination character marks the end.
+    TermChar,
+    /// A break is sent after each write, output only.
+    Break,
+}
+
+impl From<SerialEnd> for ViUInt16 {
+    fn from(value: SerialEnd) -> Self {
+        let value = match value {
+            SerialEnd::None => VI_ASRL_END_NONE,
+            SerialEnd::LastBit => VI_ASRL_END_LAST_BIT,
+            SerialEnd::TermChar => VI_ASRL_END_TERMCHAR,
+            SerialEnd::Break => VI_ASRL_END_BREAK,
+        };
+        value as ViUInt16
+    }
+}
+
+/// Word serial transfer mode used by `viVxiCommandQuery`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VxiCommandMode {
+    /// Sends a 16-bit command.
+    Command16,
+    /// Sends a 16-bit command and reads a 16-bit response.
+    Command16Response16,
+    /// Reads a 16-bit response.
+    Response16,
+    /// Sends a 32-bit command.
+    Command32,
+    /// Sends a 32-bit command and reads a 16-bit response.
+    Command32Response16,
+    /// Sends a 32-bit command and reads a 32-bit response.
+    Command32Response32,
+    /// Reads a 32-bit response.
+    Response32,
+}
+
+impl From<VxiCommandMode> for ViUInt16 {
+    fn from(value: VxiCommandMode) -> Self {
+        let value = match value {
+            VxiCommandMode::Command16 => VI_VXI_CMD16,
+            VxiCommandMode::Command16Response16 => VI_VXI_CMD16_RESP16,
+            VxiCommandMode::Response16 => VI_VXI_RESP16,
+            VxiCommandMode::Command32 => VI_VXI_CMD32,
+            VxiCommandMode::Command32Response16 => VI_VXI_CMD32_RESP16,
+            VxiCommandMode::Command32Response32 => VI_VXI_CMD32_RESP32,
+            VxiCommandMode::Response32 => VI_VXI_RESP32,
+        };
+        value as ViUInt16
+    }
+}
+
+/// Trigger protocol used by `viAssertTrigger`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerProtocol {
+    /// Interface default, for example GPIB GET or the USBTMC trigger message.
+    Default,
+    /// Asserts the trigger line.
+    On,
+    /// Deasserts the trigger line.
+    Off,
+}
+
+impl From<TriggerProtocol> for ViUInt16 {
+    fn from(value: TriggerProtocol) -> Self {
+        let value = match value {
+            TriggerProtocol::Default => VI_TRIG_PROT_DEFAULT,
+            TriggerProtocol::On => VI_TRIG_PROT_ON,
+            TriggerProtocol::Off => VI_TRIG_PROT_OFF,
+        };
+        value as ViUInt16
+    }
+}
+
+/// VXI fast data channel transfer mode (`VI_ATTR_FDC_MODE`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FdcMode {
+    Normal,
+    Stream,
+}
+
+impl From<FdcMode> for ViUInt16 {
+    fn from(value: FdcMode) -> Self {
+        let value = match value {
+            FdcMode::Normal => VI_FDC_NORMAL,
+            FdcMode::Stream => VI_FDC_STREAM,
+        };
+        value as ViUInt16
+    }
+}
+
+/// Transfer protocol of the session (`VI_ATTR_IO_PROT`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoProtocol {
+    Normal,
+    /// VXI fast data channel
+    Fdc,
+    /// GPIB high speed 488
+    Hs488,
+    /// IEEE 488.2 strings, also serial 488.2 framing
+    Strs4882,
+    /// USBTMC vendor specific messages
+    UsbtmcVendor,
+}
+
+impl From<IoProtocol> for ViUInt16 {
+    fn from(value: IoProtocol) -> Self {
+        let value = match value {
+            IoProtocol::Normal => VI_PROT_NORMAL,
+            IoProtocol::Fdc => VI_PROT_FDC,
+            IoProtocol::Hs488 => VI_PROT_HS488,
+            IoProtocol::Strs4882 => VI_PROT_4882_STRS,
+            IoProtocol::UsbtmcVendor => VI_PROT_USBTMC_VENDOR,
+        };
+        value as ViUInt16
+    }
+}
+
+impl TryFrom<ViUInt16> for IoProtocol {
+    type Error = Error;
+    fn try_from(value: ViUInt16) -> Result<Self> {
+        match value as u32 {
+            VI_PROT_NORMAL => Ok(IoProtocol::Normal),
+            VI_PROT_FDC => Ok(IoProtocol::Fdc),
+            VI_PROT_HS488 => Ok(IoProtocol::Hs488),
+            VI_PROT_4882_STRS => Ok(IoProtocol::Strs4882),
+            VI_PROT_USBTMC_VENDOR => Ok(IoProtocol::UsbtmcVendor),
+            _ => Err(Error::AttributeStateNotSupported),
+        }
+    }
+}
+
+/// Settings applied to a single read, `None` keeps the session setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadOptions {
+    /// Overrides `VI_ATTR_SUPPRESS_END_EN`.
+    pub suppress_end: Option<bool>,
+    /// Overrides `VI_ATTR_TERMCHAR_EN`.
+    pub termination_enabled: Option<bool>,
+    /// Number of bytes requested from each `viRead` call.
+    pub chunk_size: Option<usize>,
+}
+
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct FlushMode: ViUInt16 {
@@ -61,7 +327,17 @@ bitflags! {
         const IO_INPUT_BUFFER = VI_IO_IN_BUF as _;
         const IO_INPUT_BUFFER_DISCARD = VI_IO_IN_BUF_DISCARD as _;
         const IO_OUTPUT_BUFFER = VI_IO_OUT_BUF as _;
-        const IO_OUTPUT_BUFFER_DISCARD = VI_IO_OUT_BUF as _;
+        const IO_OUTPUT_BUFFER_DISCARD = VI_IO_OUT_BUF_DISCARD as _;
+    }
+}
+
+bitflags! {
+    /// Serial line errors reported by VISA since they were last checked
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct SerialErrorFlags: u8 {
+        const PARITY = 1 << 0;
+        const FRAMING = 1 << 1;
+        const OVERRUN = 1 << 2;
     }
 }
 
@@ -77,6 +353,21 @@ pub fn stringify_buffer(buffer: &[u8]) -> Result<String> {
     Ok(output)
 }
 
+/// Describes `status` through `viStatusDesc`, followed by the raw code, for
+/// example `Timeout expired before operation completed. (0xBFFF0015)`.
+pub(crate) fn status_description(session: ViSession, status: ViStatus) -> Result<String> {
+    let mut description = [0u8; 256];
+    let completion_code = unsafe {
+        CompletionCode::try_from(viStatusDesc(session, status, description.as_mut_ptr() as _))?
+    };
+    debug!(
+        "Status description read with completion code: {}",
+        completion_code
+    );
+    let description = stringify_buffer(&description)?;
+    Ok(format!("{description} ({status:#X})"))
+}
+
 /// IEEE 488.2 Mandatory Commands
 pub trait MandatoryCommands {
     fn as_session(&self) -> &Session;
@@ -94,35 +385,100 @@ pub trait MandatoryCommands {
     }
 
     fn standard_event_status_enable_query(&self) -> Result<StandardEventStatusEnableRegister> {
-        let response = self.as_session().query("*ESE?\n")?;
+        let response = self
+            .as_session()
+            .query("*ESE?\n")
+            .map_err(|error| error.in_operation("*ESE? query"))?;
         Ok(StandardEventStatusEnableRegister::try_from(
             response.as_str(),
         )?)
     }
 
     fn standard_event_status_register_query(&self) -> Result<StandardEventStatusRegister> {
-        let response = self.as_session().query("*ESR?\n")?;
+        let response = self
+            .as_session()
+            .query("*ESR?\n")
+            .map_err(|error| error.in_operation("*ESR? query"))?;
         Ok(StandardEventStatusRegister::try_from(response.as_str())?)
     }
 
     fn identification_query(&self) -> Result<Identification> {
-        let response = self.as_session().query("*IDN?\n")?;
+        let response = self
+            .as_session()
+            .query("*IDN?\n")
+            .map_err(|error| error.in_operation("*IDN? query"))?;
         Identification::try_from(response.as_str())
     }
 
+    fn individual_status_query(&self) -> Result<bool> {
+        let response = self
+            .as_session()
+            .query("*IST?\n")
+            .map_err(|error| error.in_operation("*IST? query"))?;
+        match response.trim() {
+            "0" => Ok(false),
+            "1" => Ok(true),
+            response => Err(Error::IndividualStatusQueryParse(response.to_string())),
+        }
+    }
+
     fn operation_complete_command(&self) -> Result<()> {
         self.as_session().write("*OPC\n")
     }
 
     fn operation_complete_query(&self) -> Result<bool> {
-        let response = self.as_session().query("*OPC?\n")?;
-        match response.as_str() {
+        let response = self
+            .as_session()
+            .query("*OPC?\n")
+            .map_err(|error| error.in_operation("*OPC? query"))?;
+        match response.trim() {
             "0" => Ok(false),
             "1" => Ok(true),
             response => Err(Error::OperationCompleteQueryParse(response.to_string())),
         }
     }
 
+    /// Sends `*OPC` and polls `*ESR?` until the operation complete bit is
+    /// set, failing with `Timeout` once `timeout` has elapsed.
+    ///
+    /// Polling reads and thereby clears the event status register. Completion
+    /// also confirms a command sent with `Session::write_overlapped`.
+    fn operation_complete_blocking(&self, timeout: Timeout) -> Result<()> {
+        let deadline = match timeout {
+            Timeout::Immediate => Some(Instant::now()),
+            Timeout::Custom(duration) => Some(Instant::now() + duration),
+            Timeout::Maximum | Timeout::Infinite => None,
+        };
+        self.operation_complete_command()?;
+
+        loop {
+            let register = self.standard_event_status_register_query()?;
+            if register.contains(StandardEventStatusRegister::OPERATION_COMPLETE) {
+                self.as_session()
+                    .operation_pending
+                    .store(false, Ordering::Release);
+                return Ok(());
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(Error::Timeout);
+            }
+            thread::sleep(OPERATION_COMPLETE_POLL_INTERVAL);
+        }
+    }
+
+    fn parallel_poll_enable_command(&self, register: ParallelPollEnableRegister) -> Result<()> {
+        self.as_session()
+            .write(format!("*PRE {}\n", register.value()))
+    }
+
+    fn parallel_poll_enable_query(&self) -> Result<ParallelPollEnableRegister> {
+        let response = self
+            .as_session()
+            .query("*PRE?\n")
+            .map_err(|error| error.in_operation("*PRE? query"))?;
+        ParallelPollEnableRegister::try_from(response.as_str())
+    }
+
     fn reset_command(&self) -> Result<()> {
         self.as_session().write("*RST\n")
     }
@@ -133,17 +489,26 @@ pub trait MandatoryCommands {
     }
 
     fn service_request_enable_query(&self) -> Result<ServiceRequestEnable> {
-        let response = self.as_session().query("*SRE?\n")?;
+        let response = self
+            .as_session()
+            .query("*SRE?\n")
+            .map_err(|error| error.in_operation("*SRE? query"))?;
         Ok(ServiceRequestEnable::try_from(response.as_str())?)
     }
 
     fn read_status_byte_query(&self) -> Result<StatusByteRegister> {
-        let response = self.as_session().query("*STB?\n")?;
+        let response = self
+            .as_session()
+            .query("*STB?\n")
+            .map_err(|error| error.in_operation("*STB? query"))?;
         Ok(StatusByteRegister::try_from(response.as_str())?)
     }
 
     fn self_test_query(&self) -> Result<bool> {
-        let response = self.as_session().query("*TST?\n")?;
+        let response = self
+            .as_session()
+            .query("*TST?\n")
+            .map_err(|error| error.in_operation("*TST? query"))?;
         match response.as_str() {
             "0" => Ok(true),
             "1" => Ok(false),
@@ -154,204 +519,31 @@ pub trait MandatoryCommands {
     fn wait_to_continue_command(&self) -> Result<()> {
         self.as_session().write("*WAI\n")
     }
-}
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Identification {
-    pub manufacturer: String,
-    pub model: String,
-    pub serial: String,
-    pub firmware: String,
-}
-
-impl TryFrom<&str> for Identification {
-    type Error = Error;
 
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let parts: Vec<&str> = value.trim().split(',').collect();
-
-        if parts.len() != 4 {
-            return Err(Error::IdentityParse(value.to_string()));
-        }
-
-        Ok(Self {
-            manufacturer: parts[0].to_string(),
-            model: parts[1].to_string(),
-            serial: parts[2].to_string(),
-            firmware: parts[3].to_string(),
-        })
+    /// Lists the command headers the instrument supports
+    /// (`SYSTem:HELP:HEADers?`), returned as a block of newline separated
+    /// headers.
+    fn supported_commands(&self) -> Result<Vec<String>> {
+        let session = self.as_session();
+        let block = session
+            .write("SYSTem:HELP:HEADers?\n")
+            .and_then(|_| session.read_binary_block())
+            .map_err(|error| error.in_operation("SYSTem:HELP:HEADers? query"))?;
+        let headers = String::from_utf8(block).map_err(|_| Error::InvalidUtf8)?;
+        Ok(headers
+            .lines()
+            .map(str::trim)
+            .filter(|header| !header.is_empty())
+            .map(str::to_string)
+            .collect())
     }
-}
-
-bitflags! {
-    /// Standard Event Status Register (SESR)
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct StandardEventStatusRegister : u8 {
-        /// Set when all pending operations have completed after `*OPC`.
-        const OPERATION_COMPLETE = 1 << 0;
-        /// Indicates that the device requests to become controller-in-charge.
-        const REQUEST_CONTROL = 1 << 1;
-        /// A query was improperly formed or the response queue overflowed.
-        const QUERY_ERROR = 1 << 2;
-        /// Instrument-dependent error condition.
-        const DEVICE_SPECIFIC_ERROR = 1 << 3;
-        /// Command could not be executed due to current instrument state.
-        const EXECUTION_ERROR = 1 << 4;
-        /// Syntax or semantic error in a received command.
-        const COMMAND_ERROR = 1 << 5;
-        /// Local control or front-panel action occurred.
-        const USER_REQUEST = 1 << 6;
-        /// Device power-on event detected.
-        const POWER_ON = 1 << 7;
-        // The source may set any bits.
-        const _ = !0;
-    }
-}
-
-impl TryFrom<&str> for StandardEventStatusRegister {
-    type Error = Error;
-
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let value = value
-            .trim()
-            .parse()
-            .map_err(|_| Error::StandardEventStatusRegisterParse(value.to_string()))?;
 
-        Ok(Self::from_bits_retain(value))
+    /// Queries the SCPI standard version (`SYSTem:VERSion?`) as (year, revision).
+    fn scpi_version(&self) -> Result<(u16, u16)> {
+        let response = self
+            .as_session()
+            .query("SYSTem:VERSion?\n")
+            .map_err(|error| error.in_operation("SYSTem:VERSion? query"))?;
+        parse_scpi_version(&response)
     }
 }
-
-bitflags! {
-    /// Standard Event Status Enable Register (SESER)
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct StandardEventStatusEnableRegister : u8 {
-        /// Set when all pending operations have completed after `*OPC`.
-        const OPERATION_COMPLETE = 1 << 0;
-        /// Indicates that the device requests to become controller-in-charge.
-        const REQUEST_CONTROL = 1 << 1;
-        /// A query was improperly formed or the response queue overflowed.
-        const QUERY_ERROR = 1 << 2;
-        /// Instrument-dependent error condition.
-        const DEVICE_SPECIFIC_ERROR = 1 << 3;
-        /// Command could not be executed due to current instrument state.
-        const EXECUTION_ERROR = 1 << 4;
-        /// Syntax or semantic error in a received command.
-        const COMMAND_ERROR = 1 << 5;
-        /// Local control or front-panel action occurred.
-        const USER_REQUEST = 1 << 6;
-        /// Device power-on event detected.
-        const POWER_ON = 1 << 7;
-        // The source may set any bits.
-        const _ = !0;
-    }
-}
-
-impl TryFrom<&str> for StandardEventStatusEnableRegister {
-    type Error = Error;
-
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let value = value
-            .trim()
-            .parse()
-            .map_err(|_| Error::StandardEventStatusRegisterParse(value.to_string()))?;
-
-        Ok(Self::from_bits_retain(value))
-    }
-}
-
-impl StandardEventStatusEnableRegister {
-    pub fn value(&self) -> u8 {
-        self.bits()
-    }
-}
-
-bitflags! {
-    /// Status Byte Register (STB)
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct StatusByteRegister: u8 {
-        /// Bit 3 (0x08): Questionable Status Summary (QUES).
-        ///
-        /// Set when one or more conditions in the Questionable Status
-        /// condition register group are active.
-        const QUESTIONABLE_STATUS_SUMMARY = 1 << 3;
-        /// Bit 4 (0x10): Message Available (MAV).
-        ///
-        /// Set when the instrument has one or more response messages
-        /// available in the output buffer.
-        const MESSAGE_AVAILABLE = 1 << 4;
-        /// Bit 5 (0x20): Event Status Bit (ESB).
-        ///
-        /// Set when at least one enabled event exists:
-        /// (ESR & ESE) != 0.
-        const EVENT_STATUS_BIT = 1 << 5;
-        /// Bit 6 (0x40): Request Service / Master Summary Status (RQS/MSS).
-        ///
-        /// Indicates that the instrument is requesting service (SRQ).
-        /// This bit reflects the SRQ state and is not directly writable.
-        const REQUEST_SERVICE = 1 << 6;
-        /// Bit 7 (0x80): Operation Status Summary (OPER).
-        ///
-        /// Set when one or more conditions in the Operation Status
-        /// condition register group are active.
-        const OPERATION_STATUS_SUMMARY = 1 << 7;
-        // The source may set any bits.
-        const _ = !0;
-    }
-}
-
-impl TryFrom<&str> for StatusByteRegister {
-    type Error = Error;
-
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let value = value
-            .trim()
-            .parse()
-            .map_err(|_| Error::StatusByteRegisterQueryParse(value.to_string()))?;
-
-        Ok(Self::from_bits_retain(value))
-    }
-}
-
-bitflags! {
-    /// Service Request Enable Register (SRE)
-    ///
-    /// Determines which bits in the Status Byte Register will
-    /// generate a service request (SRQ) when set.
-    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct ServiceRequestEnable: u8 {
-        /// Bit 3: Enable service request on Questionable Status
-        const QUESTIONABLE_STATUS = 1 << 3;
-        /// Bit 4: Enable service request on Message Available
-        const MESSAGE_AVAILABLE = 1 << 4;
-        /// Bit 5: Enable service request on Event Status
-        const EVENT_STATUS = 1 << 5;
-        // Bit 6 (RQS/MSS) is read-only and cannot be enabled
-        /// Bit 7: Enable service request on Operation Status
-        const OPERATION_STATUS = 1 << 7;
-
-        const _ = !0;
-    }
-}
-
-impl TryFrom<&str> for ServiceRequestEnable {
-    type Error = Error;
-
-    fn try_from(value: &str) -> std::result::Result<Self, Self::Error> {
-        let value = value
-            .trim()
-            .parse()
-            .map_err(|_| Error::ServiceRequestEnableQueryParse(value.to_string()))?;
-
-        Ok(Self::from_bits_retain(value))
-    }
-}
-
-impl ServiceRequestEnable {
-    pub fn value(&self) -> u8 {
-        self.bits()
-    }
-}
-
-pub trait AsScpi {
-    fn as_scpi(&self) -> String;
-}